@@ -0,0 +1,140 @@
+//! Checks collectors by initializing them and collecting once, e.g., to validate a config before it is deployed.
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::collectors::{Collector, Id};
+use crate::config::Config;
+use crate::scheduler::{COLLECTOR_THREAD_NAME_PREFIX, DEFAULT_COLLECTOR_TIMEOUT_SEC, panic_message};
+
+#[derive(Debug)]
+pub struct CheckResult {
+    pub id: Id,
+    pub samples: usize,
+    pub duration: Duration,
+    /// Reason of the failure; `None` if the check passed
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Initializes each collector, collects once, and shuts the collector down again. Collectors are checked in parallel;
+/// a collector that does not finish within its timeout fails.
+pub fn check_collectors(collectors: Vec<Box<dyn Collector + Send>>, config: &Config) -> Vec<CheckResult> {
+    let default_timeout = config.CollectorTimeout.unwrap_or(DEFAULT_COLLECTOR_TIMEOUT_SEC);
+    let started = Instant::now();
+
+    let checks: Vec<_> = collectors.into_iter().map(|mut collector| {
+        let id = collector.id().clone();
+        let timeout = Duration::from_secs(collector.get_timeout().unwrap_or(default_timeout));
+        let (tx, rx) = mpsc::channel();
+        // Named like the scheduler's collector threads, so a panic is reported instead of aborting.
+        let check_thread = thread::Builder::new()
+            .name(format!("{}{}", COLLECTOR_THREAD_NAME_PREFIX, id))
+            .spawn(move || {
+                let started = Instant::now();
+                let result = collector.init()
+                    .map_err(|err| err.to_string())
+                    .and_then(|_| collector.collect().map_err(|err| err.to_string()));
+                collector.shutdown();
+                let _ = tx.send((result.map(|samples| samples.len()), started.elapsed()));
+            })
+            .expect("Failed to spawn collector thread");
+
+        (id, timeout, rx, check_thread)
+    }).collect();
+
+    checks.into_iter().map(|(id, timeout, rx, check_thread)| {
+        match rx.recv_timeout(timeout.checked_sub(started.elapsed()).unwrap_or_default()) {
+            Ok((Ok(samples), duration)) => CheckResult { id, samples, duration, error: None },
+            Ok((Err(err), duration)) => CheckResult { id, samples: 0, duration, error: Some(err) },
+            Err(RecvTimeoutError::Timeout) => {
+                let error = format!("collection did not finish within its timeout of {} sec", timeout.as_secs());
+                CheckResult { id, samples: 0, duration: timeout, error: Some(error) }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let reason = match check_thread.join() {
+                    Err(payload) => panic_message(&*payload),
+                    Ok(_) => "unknown reason".to_string(),
+                };
+                CheckResult { id, samples: 0, duration: started.elapsed(), error: Some(format!("collector panicked: {}", reason)) }
+            }
+        }
+    }).collect()
+}
+
+/// Formats the results as table with one line per collector.
+pub fn format_results(results: &[CheckResult]) -> String {
+    let width = results.iter().map(|r| r.id.len()).chain(Some("COLLECTOR".len())).max().unwrap_or(0);
+    let mut table = format!("{:<width$}  {:<6}  {:>7}  {:>8}  {}\n", "COLLECTOR", "RESULT", "SAMPLES", "DURATION", "REASON", width = width);
+    for r in results {
+        let line = format!("{:<width$}  {:<6}  {:>7}  {:>5} ms  {}",
+                           r.id,
+                           if r.passed() { "PASS" } else { "FAIL" },
+                           r.samples,
+                           r.duration.as_millis(),
+                           r.error.as_deref().unwrap_or(""),
+                           width = width);
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bosun::{Metadata, Sample};
+    use crate::collectors::Error;
+
+    struct Dummy {
+        id: Id,
+        fail_init: bool,
+        hang: bool,
+    }
+
+    impl Collector for Dummy {
+        fn init(&mut self) -> Result<(), Box<Error>> {
+            if self.fail_init {
+                return Err(Box::new(Error::InitError("connection refused".to_string())));
+            }
+            Ok(())
+        }
+        fn id(&self) -> &Id { &self.id }
+        fn metadata(&self) -> Vec<Metadata> { Vec::new() }
+        fn collect(&self) -> Result<Vec<Sample>, Error> {
+            if self.hang {
+                thread::sleep(Duration::from_secs(5));
+            }
+            Ok(vec![Sample::new("test", 1f64)])
+        }
+        fn shutdown(&mut self) {}
+        fn get_timeout(&self) -> Option<u64> { if self.hang { Some(1) } else { None } }
+    }
+
+    fn dummy(id: &str, fail_init: bool, hang: bool) -> Box<dyn Collector + Send> {
+        Box::new(Dummy { id: id.to_string(), fail_init, hang })
+    }
+
+    #[test]
+    fn check_collectors_reports_pass_and_fail() {
+        let collectors = vec![dummy("ok", false, false), dummy("refused", true, false), dummy("hung", false, true)];
+
+        let results = check_collectors(collectors, &Config::default());
+
+        assert_eq!(results[0].passed(), true);
+        assert_eq!(results[0].samples, 1);
+        assert_eq!(results[1].error.as_ref().unwrap(), "Collectors error: Init failed because connection refused");
+        assert!(results[2].error.as_ref().unwrap().contains("timeout of 1 sec"));
+
+        let table = format_results(&results);
+        assert!(table.starts_with("COLLECTOR  RESULT"));
+        assert!(table.contains("refused    FAIL"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Circuit breaker guarding the transmission of samples to an unreachable server.
+//!
+//! The breaker starts `Healthy`. A failed request makes it `Degraded`; after `failure_threshold` consecutive failures
+//! the circuit opens and no requests are allowed until an exponentially growing, jittered backoff has passed. Then a
+//! single probe request is allowed: success closes the circuit, failure opens it again with a longer backoff.
+
+use rand::{self, Rng};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+static DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+static DEFAULT_BACKOFF_BASE_SEC: u64 = 30;
+static DEFAULT_BACKOFF_MAX_SEC: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    Healthy,
+    Degraded,
+    Open,
+}
+
+impl CircuitState {
+    /// Numeric representation for the `rs-collector.bosun.circuit_state` metric.
+    pub fn value(&self) -> f64 {
+        match *self {
+            CircuitState::Healthy => 0f64,
+            CircuitState::Degraded => 1f64,
+            CircuitState::Open => 2f64,
+        }
+    }
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CircuitState::Healthy => write!(f, "healthy"),
+            CircuitState::Degraded => write!(f, "degraded"),
+            CircuitState::Open => write!(f, "open"),
+        }
+    }
+}
+
+pub struct CircuitBreaker {
+    name: String,
+    state: CircuitState,
+    consecutive_failures: u32,
+    failure_threshold: u32,
+    backoff_base: Duration,
+    backoff_max: Duration,
+    retry_at: Option<Instant>,
+    suppressed_errors: u64,
+}
+
+impl CircuitBreaker {
+    pub fn new<T: Into<String>>(name: T) -> CircuitBreaker {
+        CircuitBreaker::with_backoff(name,
+                                     DEFAULT_FAILURE_THRESHOLD,
+                                     Duration::from_secs(DEFAULT_BACKOFF_BASE_SEC),
+                                     Duration::from_secs(DEFAULT_BACKOFF_MAX_SEC))
+    }
+
+    pub fn with_backoff<T: Into<String>>(name: T, failure_threshold: u32, backoff_base: Duration, backoff_max: Duration)
+        -> CircuitBreaker {
+        CircuitBreaker {
+            name: name.into(),
+            state: CircuitState::Healthy,
+            consecutive_failures: 0,
+            failure_threshold: failure_threshold.max(1),
+            backoff_base,
+            backoff_max,
+            retry_at: None,
+            suppressed_errors: 0,
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Returns whether a request may be sent now. An open circuit allows a probe request once its backoff has passed.
+    pub fn allows_request(&self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Healthy | CircuitState::Degraded => true,
+            CircuitState::Open => self.retry_at.map_or(true, |retry_at| now >= retry_at),
+        }
+    }
+
+    pub fn on_success(&mut self) {
+        if self.state != CircuitState::Healthy {
+            info!("{} is reachable again after {} failed attempts; {} errors have been suppressed. Circuit is healthy.",
+                  self.name, self.consecutive_failures, self.suppressed_errors);
+        }
+        self.state = CircuitState::Healthy;
+        self.consecutive_failures = 0;
+        self.retry_at = None;
+        self.suppressed_errors = 0;
+    }
+
+    pub fn on_failure(&mut self, now: Instant, reason: &str) {
+        self.consecutive_failures += 1;
+
+        if self.consecutive_failures < self.failure_threshold {
+            if self.state == CircuitState::Healthy {
+                error!("Failed to reach {}, because {}. Circuit is degraded.", self.name, reason);
+            } else {
+                debug!("Failed to reach {}, because {}.", self.name, reason);
+                self.suppressed_errors += 1;
+            }
+            self.state = CircuitState::Degraded;
+            return;
+        }
+
+        let backoff = self.backoff();
+        if self.state != CircuitState::Open {
+            error!("Failed to reach {} {} times in a row, because {}. Opening circuit; next attempt in {} sec.",
+                   self.name, self.consecutive_failures, reason, backoff.as_secs());
+        } else {
+            debug!("Probe to {} failed, because {}. Next attempt in {} sec.", self.name, reason, backoff.as_secs());
+            self.suppressed_errors += 1;
+        }
+        self.state = CircuitState::Open;
+        self.retry_at = Some(now + backoff);
+    }
+
+    /// Exponential backoff with jitter: the delay doubles with every failure beyond the threshold up to the max, and a
+    /// random value between half and the full delay is chosen to spread retries of many collectors.
+    fn backoff(&self) -> Duration {
+        let exponent = (self.consecutive_failures - self.failure_threshold).min(16);
+        let delay = self.backoff_base.as_secs().saturating_mul(1 << exponent).min(self.backoff_max.as_secs());
+        let min = delay / 2;
+        let jittered = if delay > min { rand::thread_rng().gen_range(min, delay + 1) } else { delay };
+
+        Duration::from_secs(jittered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit_breaker() -> CircuitBreaker {
+        CircuitBreaker::with_backoff("test", 2, Duration::from_secs(10), Duration::from_secs(40))
+    }
+
+    #[test]
+    fn failures_degrade_and_open_circuit() {
+        let mut cb = circuit_breaker();
+        let now = Instant::now();
+        assert_eq!(cb.state(), CircuitState::Healthy);
+
+        cb.on_failure(now, "test");
+        assert_eq!(cb.state(), CircuitState::Degraded);
+        assert_eq!(cb.allows_request(now), true);
+
+        cb.on_failure(now, "test");
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert_eq!(cb.allows_request(now), false);
+        assert_eq!(cb.allows_request(now + Duration::from_secs(10)), true);
+    }
+
+    #[test]
+    fn success_closes_circuit() {
+        let mut cb = circuit_breaker();
+        let now = Instant::now();
+        cb.on_failure(now, "test");
+        cb.on_failure(now, "test");
+
+        cb.on_success();
+
+        assert_eq!(cb.state(), CircuitState::Healthy);
+        assert_eq!(cb.allows_request(now), true);
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let mut cb = circuit_breaker();
+        let now = Instant::now();
+
+        cb.on_failure(now, "test");
+        cb.on_failure(now, "test");
+        assert!(cb.allows_request(now + Duration::from_secs(4)) == false);
+        assert!(cb.allows_request(now + Duration::from_secs(10)));
+
+        cb.on_failure(now, "test");
+        assert!(cb.allows_request(now + Duration::from_secs(9)) == false);
+        assert!(cb.allows_request(now + Duration::from_secs(20)));
+
+        for _ in 0..10 {
+            cb.on_failure(now, "test");
+        }
+        assert!(cb.allows_request(now + Duration::from_secs(19)) == false);
+        assert!(cb.allows_request(now + Duration::from_secs(40)));
+    }
+}
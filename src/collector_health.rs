@@ -0,0 +1,190 @@
+//! Failure state machine of a single collector.
+//!
+//! A collector starts `Healthy`. A failed collection makes it `Failing`: the next collection is delayed by an
+//! exponentially growing, jittered backoff and preceded by re-initializing the collector. After `max_failures`
+//! consecutive failures, the collector is `Quarantined` and only probed every `probe_interval`. Any successful
+//! collection makes it `Healthy` again.
+
+use rand::{self, Rng};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+static DEFAULT_BACKOFF_BASE_SEC: u64 = 15;
+static DEFAULT_BACKOFF_MAX_SEC: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HealthState {
+    Healthy,
+    Failing,
+    Quarantined,
+}
+
+impl HealthState {
+    /// Numeric representation for the `rs-collector.collector.state` metric.
+    pub fn value(&self) -> f64 {
+        match *self {
+            HealthState::Healthy => 0f64,
+            HealthState::Failing => 1f64,
+            HealthState::Quarantined => 2f64,
+        }
+    }
+}
+
+impl fmt::Display for HealthState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HealthState::Healthy => write!(f, "healthy"),
+            HealthState::Failing => write!(f, "failing"),
+            HealthState::Quarantined => write!(f, "quarantined"),
+        }
+    }
+}
+
+pub struct CollectorHealth {
+    id: String,
+    state: HealthState,
+    consecutive_failures: u32,
+    max_failures: u32,
+    backoff_base: Duration,
+    backoff_max: Duration,
+    probe_interval: Duration,
+    retry_at: Option<Instant>,
+}
+
+impl CollectorHealth {
+    pub fn new<T: Into<String>>(id: T, max_failures: u32, probe_interval: Duration) -> CollectorHealth {
+        CollectorHealth::with_backoff(id,
+                                      max_failures,
+                                      probe_interval,
+                                      Duration::from_secs(DEFAULT_BACKOFF_BASE_SEC),
+                                      Duration::from_secs(DEFAULT_BACKOFF_MAX_SEC))
+    }
+
+    pub fn with_backoff<T: Into<String>>(id: T,
+                                         max_failures: u32,
+                                         probe_interval: Duration,
+                                         backoff_base: Duration,
+                                         backoff_max: Duration)
+        -> CollectorHealth {
+        CollectorHealth {
+            id: id.into(),
+            state: HealthState::Healthy,
+            consecutive_failures: 0,
+            max_failures: max_failures.max(1),
+            backoff_base,
+            backoff_max,
+            probe_interval,
+            retry_at: None,
+        }
+    }
+
+    pub fn state(&self) -> HealthState {
+        self.state
+    }
+
+    /// Returns whether the collector may collect now. A failing or quarantined collector may collect once its backoff
+    /// or probe interval has passed.
+    pub fn allows_collection(&self, now: Instant) -> bool {
+        self.retry_at.map_or(true, |retry_at| now >= retry_at)
+    }
+
+    /// Returns when a failing or quarantined collector may collect again.
+    pub fn retry_at(&self) -> Option<Instant> {
+        self.retry_at
+    }
+
+    /// Returns whether the collector must be re-initialized before its next collection.
+    pub fn needs_init(&self) -> bool {
+        self.state != HealthState::Healthy
+    }
+
+    pub fn on_success(&mut self) {
+        if self.state != HealthState::Healthy {
+            info!("Collector {} recovered after {} failed collections.", self.id, self.consecutive_failures);
+        }
+        self.state = HealthState::Healthy;
+        self.consecutive_failures = 0;
+        self.retry_at = None;
+    }
+
+    pub fn on_failure(&mut self, now: Instant, reason: &str) {
+        self.consecutive_failures += 1;
+
+        if self.consecutive_failures < self.max_failures {
+            let backoff = self.backoff();
+            if self.state == HealthState::Healthy {
+                error!("Collector {} failed, because {}. Next attempt in {} sec.", self.id, reason, backoff.as_secs());
+            } else {
+                debug!("Collector {} failed again, because {}. Next attempt in {} sec.", self.id, reason, backoff.as_secs());
+            }
+            self.state = HealthState::Failing;
+            self.retry_at = Some(now + backoff);
+            return;
+        }
+
+        if self.state != HealthState::Quarantined {
+            error!("Collector {} failed {} times in a row, because {}. Quarantining collector; probing every {} sec.",
+                   self.id, self.consecutive_failures, reason, self.probe_interval.as_secs());
+        } else {
+            debug!("Probe of collector {} failed, because {}.", self.id, reason);
+        }
+        self.state = HealthState::Quarantined;
+        self.retry_at = Some(now + self.probe_interval);
+    }
+
+    /// Exponential backoff with jitter: the delay doubles with every failure up to the max, and a random value between
+    /// half and the full delay is chosen so that failing collectors do not retry in lockstep.
+    fn backoff(&self) -> Duration {
+        let exponent = (self.consecutive_failures - 1).min(16);
+        let delay = self.backoff_base.as_secs().saturating_mul(1 << exponent).min(self.backoff_max.as_secs());
+        let min = delay / 2;
+        let jittered = if delay > min { rand::thread_rng().gen_range(min, delay + 1) } else { delay };
+
+        Duration::from_secs(jittered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collector_health() -> CollectorHealth {
+        CollectorHealth::with_backoff("test", 3, Duration::from_secs(100), Duration::from_secs(10), Duration::from_secs(15))
+    }
+
+    #[test]
+    fn failures_back_off_up_to_max() {
+        let mut health = collector_health();
+        let now = Instant::now();
+        assert_eq!(health.state(), HealthState::Healthy);
+        assert_eq!(health.needs_init(), false);
+
+        health.on_failure(now, "test");
+        assert_eq!(health.state(), HealthState::Failing);
+        assert_eq!(health.needs_init(), true);
+        assert_eq!(health.allows_collection(now + Duration::from_secs(4)), false);
+        assert_eq!(health.allows_collection(now + Duration::from_secs(10)), true);
+
+        health.on_failure(now, "test");
+        assert_eq!(health.state(), HealthState::Failing);
+        assert_eq!(health.allows_collection(now + Duration::from_secs(6)), false);
+        assert_eq!(health.allows_collection(now + Duration::from_secs(15)), true);
+    }
+
+    #[test]
+    fn max_failures_quarantine_collector_until_success() {
+        let mut health = collector_health();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            health.on_failure(now, "test");
+        }
+        assert_eq!(health.state(), HealthState::Quarantined);
+        assert_eq!(health.allows_collection(now + Duration::from_secs(99)), false);
+        assert_eq!(health.allows_collection(now + Duration::from_secs(100)), true);
+
+        health.on_success();
+        assert_eq!(health.state(), HealthState::Healthy);
+        assert_eq!(health.allows_collection(now), true);
+    }
+}
@@ -1,25 +1,66 @@
 // See http://galeracluster.com/documentation-webpages/monitoringthecluster.html
 
-use crate::bosun::{Metadata, Rate, Sample};
+use crate::bosun::{Metadata, Rate, Sample, Tags};
 use crate::collectors::*;
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
 use crate::utils;
 
 use mysql as my;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug)]
-#[derive(RustcDecodable)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
 pub struct GaleraConfig {
     pub User: Option<String>,
     pub Password: Option<String>,
+    pub PasswordFile: Option<String>,
     pub Socket: Option<String>,
     pub Host: Option<String>,
     pub UseSsl: Option<bool>,
     pub CaCert: Option<String>,
     pub ClientCert: Option<String>,
     pub ClientCertKey: Option<String>,
+    pub Timeout: Option<u64>,
+    pub Interval: Option<String>,
+    pub Tags: Option<Tags>,
+    pub Filter: Option<FilterConfig>,
+}
+
+impl GaleraConfig {
+    pub fn apply_defaults(&mut self) {
+        self.UseSsl.get_or_insert(false);
+    }
+
+    /// Reads `Password` from `PasswordFile`, if set.
+    pub fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        if let Some(ref path) = self.PasswordFile {
+            if self.Password.is_some() {
+                return Err(ConfigError::invalid("Galera", "PasswordFile", "must not be set together with Password"));
+            }
+            let password = utils::read_secret_file(path).map_err(|err| ConfigError::invalid("Galera", "PasswordFile", err))?;
+            self.Password = Some(password);
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.UseSsl.unwrap_or(false) && self.CaCert.is_none() {
+            return Err(ConfigError::invalid("Galera", "CaCert", "a CA cert is required, because UseSsl is set"));
+        }
+        if self.ClientCert.is_some() && self.ClientCertKey.is_none() {
+            return Err(ConfigError::invalid("Galera", "ClientCertKey", "a client key is required, because ClientCert is set"));
+        }
+        parse_interval(&self.Interval).map_err(|err| ConfigError::invalid("Galera", "Interval", err))?;
+        validate_tags(&self.Tags).map_err(|err| ConfigError::invalid("Galera", "Tags", err))?;
+        compile_filter(&self.Filter, "Galera.Filter")?;
+
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -34,9 +75,13 @@ pub struct Galera {
     client_cert: Option<PathBuf>,
     client_cert_key: Option<PathBuf>,
     pool: Option<my::Pool>,
+    timeout: Option<u64>,
+    interval: Option<Duration>,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
 }
 
-pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
     match config.Galera {
         Some(ref config) => {
             let id = format!("galera#{}@{}{}",
@@ -44,7 +89,6 @@ pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
                              config.Socket.as_ref().unwrap_or(&"".to_string()),
                              config.Host.as_ref().unwrap_or(&"".to_string()),
                             );
-
             let collector = Galera {
                 id: id.clone(),
                 user: config.User.clone(),
@@ -56,22 +100,16 @@ pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
                 client_cert: config.ClientCert.as_ref().map(|s| s.into()),
                 client_cert_key: config.ClientCertKey.as_ref().map(|s| s.into()),
                 pool: None,
+                timeout: config.Timeout,
+                interval: parse_interval(&config.Interval).map_err(|err| ConfigError::invalid("Galera", "Interval", err))?,
+                tags: config.Tags.clone(),
+                filter: compile_filter(&config.Filter, "Galera.Filter")?,
             };
-
-            // TODO: This should be handled by the parser, but that requires serde
-            if collector.use_ssl && collector.ca_cert.is_none() {
-                error!("Failed to create instance of Galera collector id='{}', because SSL is activated without CA cert", id);
-                Vec::new()
-            } else if collector.client_cert.is_some() && collector.client_cert_key.is_none() {
-                error!("Failed to create instance of Galera collector id='{}', because client cert is set without client key", id);
-                Vec::new()
-            } else {
-                info!("Created instance of Galera collector: {}", id);
-                vec![Box::new(collector)]
-            }
+            info!("Created instance of Galera collector: {}", id);
+            Ok(vec![Box::new(collector)])
         }
         None => {
-            Vec::new()
+            Ok(Vec::new())
         }
     }
 }
@@ -162,6 +200,22 @@ impl Collector for Galera {
         self.pool = None;
     }
 
+    fn get_timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_tags(&self) -> Option<&Tags> {
+        self.tags.as_ref()
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
     fn metadata(&self) -> Vec<Metadata> {
         vec![
             Metadata::new( "galera.wsrep.local.state.uuid", Rate::Gauge, "",
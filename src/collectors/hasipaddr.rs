@@ -1,6 +1,7 @@
 use crate::bosun::{Metadata, Rate, Sample, Tags};
 use crate::collectors::*;
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
 
 use get_if_addrs::{get_if_addrs, IfAddr};
 use itertools::Itertools;
@@ -10,37 +11,67 @@ use std::iter::FromIterator;
 use std::io;
 use std::net::Ipv4Addr;
 use std::str::FromStr;
+use std::time::Duration;
 
 
 static HAS_IP_METRIC_NAME: &'static str = "os.net.has_ipv4s";
 static IPV4_TAG: &'static str = "ipv4";
 
 #[derive(Debug)]
-#[derive(RustcDecodable)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
 pub struct HasIpAddrConfig {
     pub Ipv4: Vec<String>,
+    pub Interval: Option<String>,
+    pub Tags: Option<Tags>,
+    pub Filter: Option<FilterConfig>,
+}
+
+impl HasIpAddrConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for ip in &self.Ipv4 {
+            Ipv4Addr::from_str(ip)
+                .map_err(|err| ConfigError::invalid("HasIpAddr", "Ipv4", format!("'{}' is not an IPv4 address: {}", ip, err)))?;
+        }
+        parse_interval(&self.Interval).map_err(|err| ConfigError::invalid("HasIpAddr", "Interval", err))?;
+        validate_tags(&self.Tags).map_err(|err| ConfigError::invalid("HasIpAddr", "Tags", err))?;
+        compile_filter(&self.Filter, "HasIpAddr.Filter")?;
+
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
 pub struct HasIpAddr {
     id: Id,
     ipv4: Vec<Ipv4Addr>,
+    interval: Option<Duration>,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
 }
 
-pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
     match config.HasIpAddr {
         Some(ref config) => {
             let id = format!("hasipaddr#{}", config.Ipv4.iter().join(","));
+            let ipv4 = config.Ipv4.iter()
+                .map(|ip| Ipv4Addr::from_str(ip)
+                    .map_err(|err| ConfigError::invalid("HasIpAddr", "Ipv4", format!("'{}' is not an IPv4 address: {}", ip, err))))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let collector = HasIpAddr {
+                id: id.clone(),
+                ipv4: ipv4,
+                interval: parse_interval(&config.Interval).map_err(|err| ConfigError::invalid("HasIpAddr", "Interval", err))?,
+                tags: config.Tags.clone(),
+                filter: compile_filter(&config.Filter, "HasIpAddr.Filter")?,
+            };
             info!("Created instance of HasIpAddr collector: {}", id);
-
-            // TODO: Take care of failure case -- ip addr cannot be converted.
-            let ipv4 = config.Ipv4.iter().map(|ip| Ipv4Addr::from_str(&ip).unwrap()).collect();
-            let collector = HasIpAddr { id: id, ipv4: ipv4 };
-            vec![Box::new(collector)]
+            Ok(vec![Box::new(collector)])
         }
         None => {
-            Vec::new()
+            Ok(Vec::new())
         }
     }
 }
@@ -62,6 +93,18 @@ impl Collector for HasIpAddr {
 
     fn shutdown(&mut self) {}
 
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_tags(&self) -> Option<&Tags> {
+        self.tags.as_ref()
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
     fn metadata(&self) -> Vec<Metadata> {
         vec![
             Metadata::new(HAS_IP_METRIC_NAME,
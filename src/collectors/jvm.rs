@@ -1,40 +1,84 @@
 use crate::bosun::{Metadata, Rate, Sample, Tags};
 use crate::collectors::*;
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
+use crate::sanitizer::{self, InvalidNamePolicy};
 
 use regex::Regex;
 use std::collections::HashMap;
 use std::process::{Command, Output};
 use std::io::Result as IoResult;
+use std::time::Duration;
 
 static METRIC_NAME_GC: &'static str = "jvm.gc.stats";
 
 #[derive(Debug)]
 #[derive(Clone)]
-#[derive(RustcDecodable)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
 pub struct JvmConfig {
     Command: String,
-    Name: String,
+    pub Name: String,
+    pub Timeout: Option<u64>,
+    pub Interval: Option<String>,
+    Tags: Option<Tags>,
+    pub Filter: Option<FilterConfig>,
+}
+
+impl JvmConfig {
+    /// Checks the settings; an invalid `Name` is only an error if invalid names are rejected, otherwise it is sanitized
+    /// by `create_instances`.
+    pub fn validate(&self, invalid_names: InvalidNamePolicy) -> Result<(), ConfigError> {
+        // The name becomes the value of tag `jvm_name`.
+        if invalid_names == InvalidNamePolicy::Reject && !sanitizer::is_valid(&self.Name) {
+            return Err(ConfigError::invalid("Jvm", "Name",
+                format!("'{}' must only contain letters, digits, '-', '_', '.', and '/'", self.Name)));
+        }
+        let section = format!("Jvm.{}", self.Name);
+        parse_interval(&self.Interval).map_err(|err| ConfigError::invalid(section.clone(), "Interval", err))?;
+        validate_tags(&self.Tags).map_err(|err| ConfigError::invalid(section.clone(), "Tags", err))?;
+        compile_filter(&self.Filter, &format!("{}.Filter", section))?;
+
+        Ok(())
+    }
 }
 
 pub struct Jvm {
     id: Id,
-    jvms: Vec<JvmConfig>,
+    jvm: JvmConfig,
     metadata: HashMap<String, Metadata>,
+    timeout: Option<u64>,
+    interval: Option<Duration>,
+    filter: Option<MetricFilter>,
 }
 
-pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
-    if !config.Jvm.is_empty() {
-        let id = "jvm".to_string();
-        info!("Created instance of JVM collector: {}", id);
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
+    config.Jvm.iter().map(create_instance).collect()
+}
 
-        let metadata = metadata();
-        let collector = Jvm { id: id, jvms: config.Jvm.clone(), metadata: metadata };
-        vec![Box::new(collector)]
-    } else {
-        Vec::new()
-    }
+/// Creates the JVM collector instance of a single `[[Jvm]]` section.
+pub fn create_instance(jvm: &JvmConfig) -> Result<Box<dyn Collector + Send>, ConfigError> {
+    let id = instance_id(jvm);
+    let section = format!("Jvm.{}", jvm.Name);
+    let collector = Jvm {
+        id: id.clone(),
+        jvm: JvmConfig { Name: sanitizer::sanitize(&jvm.Name), ..jvm.clone() },
+        metadata: metadata(),
+        timeout: jvm.Timeout,
+        interval: parse_interval(&jvm.Interval).map_err(|err| ConfigError::invalid(section.clone(), "Interval", err))?,
+        filter: compile_filter(&jvm.Filter, &format!("{}.Filter", section))?,
+    };
+    info!("Created instance of JVM collector: {}", id);
+    Ok(Box::new(collector))
+}
+
+fn instance_id(config: &JvmConfig) -> Id {
+    format!("jvm#{}", config.Name)
+}
+
+/// Returns the config of the JVM collector instance with the given id.
+pub fn find_config<'a>(config: &'a Config, id: &Id) -> Option<&'a JvmConfig> {
+    config.Jvm.iter().find(|jvm| &instance_id(jvm) == id)
 }
 
 impl Collector for Jvm {
@@ -58,7 +102,7 @@ impl Collector for Jvm {
     fn collect(&self) -> Result<Vec<Sample>, Error> {
         let jvm_processes = r#try!(get_jps());
         let results: Vec<Result<Vec<GcStat>, Error>> = jvm_processes.iter()
-            .map(|jp| identify_jvms(&self.jvms, jp))
+            .map(|jp| identify_jvm(&self.jvm, jp))
             .filter(|jvm| jvm.name.is_some())
             .map(|jvm| sample_gc_stats(&jvm))
             .collect();
@@ -84,6 +128,18 @@ impl Collector for Jvm {
 
     fn shutdown(&mut self) {}
 
+    fn get_timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
     fn metadata(&self) -> Vec<Metadata> {
         let mut metadata = metadata();
         let result = metadata.drain().map(|(_, v)| v).collect();
@@ -258,26 +314,27 @@ fn execute_jps() -> IoResult<Output> {
 struct IdentifiedJvm {
     pid: u32,
     name: Option<String>,
+    tags: Tags,
 }
 
-fn identify_jvms(jvm_configs: &Vec<JvmConfig>, jp: &JvmProcess) -> IdentifiedJvm {
+fn identify_jvm(jvm_config: &JvmConfig, jp: &JvmProcess) -> IdentifiedJvm {
     let mut name = None;
-    for jvm_config in jvm_configs {
-        if let Ok(re) = Regex::new(&jvm_config.Command) {
-            if re.is_match(&jp.class) || re.is_match(&jp.cmdline) {
-                name = Some(jvm_config.Name.clone());
-                break;
-            } else {
-                debug!("Regular expression '{:?}' did not match JVM process '{:?}'", jvm_config, jp);
-            }
+    let mut tags = Tags::new();
+    if let Ok(re) = Regex::new(&jvm_config.Command) {
+        if re.is_match(&jp.class) || re.is_match(&jp.cmdline) {
+            name = Some(jvm_config.Name.clone());
+            tags = jvm_config.Tags.clone().unwrap_or_default();
+        } else {
+            debug!("Regular expression '{:?}' did not match JVM process '{:?}'", jvm_config, jp);
         }
     }
-    IdentifiedJvm {pid: jp.pid, name: name }
+    IdentifiedJvm {pid: jp.pid, name: name, tags: tags }
 }
 
 #[derive(Debug)]
 struct GcStat {
     jvm_name: String,
+    tags: Tags,
     name: String,
     value: f64,
 }
@@ -300,7 +357,7 @@ fn sample_gc_stats(jvm: &IdentifiedJvm) -> Result<Vec<GcStat>, Error> {
         let name = names[i];
         let value = r#try!(values[i].parse::<f64>());
         // Unwrap is safe, but only due to the filter in the main algorithm
-        let gcstat = GcStat{ jvm_name: jvm.name.as_ref().unwrap().clone(), name: name.to_string(), value: value };
+        let gcstat = GcStat{ jvm_name: jvm.name.as_ref().unwrap().clone(), tags: jvm.tags.clone(), name: name.to_string(), value: value };
         trace!("Successfully run gcstat for JVM Process '{:?}': '{:?}'", jvm, gcstat);
         gcstats.push(gcstat);
     }
@@ -315,7 +372,7 @@ fn execute_jstat(jvm: &IdentifiedJvm) -> IoResult<Output> {
 }
 
 fn gcstat_to_sample(metadata: &HashMap<String, Metadata>, gcstat: GcStat) -> Option<Sample> {
-    let mut tags = Tags::new();
+    let mut tags = gcstat.tags;
     tags.insert("jvm_name".to_string(), gcstat.jvm_name );
     let metric_name = metadata.get(&gcstat.name).map(|m| m.metric.to_string());
     if let Some(name) = metric_name {
@@ -1,47 +1,77 @@
 use crate::bosun::{Metadata, Rate, Sample, Tags};
 use crate::collectors::*;
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
 use is_executable::IsExecutable;
 use std::path::Path;
 use regex::Regex;
 use std::process::{Command, Output};
 use std::io::Result as IoResult;
+use std::time::Duration;
 
 static METRIC_PREFIX: &'static str = "hw.storage.drivestats";
 static MEGA_DEFAULT_BINARY: &'static str = "/opt/MegaRAID/MegaCli/MegaCli64";
 static MEGA_PARAM_LDPDINFO: &'static str = "-LdPdInfo";
 static MEGA_PARAM_ALL_ADAPTERS: &'static str = "ALL";
+static LEGACY_TICK_SEC: u64 = 15;
 
 pub struct Megaraid {
     id: Id,
-    tick_interval: i32,
+    interval: Option<Duration>,
+    timeout: Option<u64>,
     megacli_command: String,
     adapter: String,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
 }
 
 #[derive(Debug)]
 #[derive(Clone)]
-#[derive(RustcDecodable)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
 pub struct MegaraidConfig {
-    tick_interval: Option<i32>,
+    interval: Option<String>,
+    // Deprecated: multiple of 15 seconds; use `interval`
+    tick_interval: Option<u64>,
+    timeout: Option<u64>,
     megacli_command: Option<String>,
     adapter: Option<i32>,
+    tags: Option<Tags>,
+    filter: Option<FilterConfig>,
 }
 
-pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
+impl MegaraidConfig {
+    pub fn apply_defaults(&mut self) {
+        self.megacli_command.get_or_insert_with(|| MEGA_DEFAULT_BINARY.to_string());
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        parse_interval(&self.interval).map_err(|err| ConfigError::invalid("Megaraid", "interval", err))?;
+        validate_tags(&self.tags).map_err(|err| ConfigError::invalid("Megaraid", "tags", err))?;
+        compile_filter(&self.filter, "Megaraid.filter")?;
+
+        Ok(())
+    }
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
     if let Some(ref cfg) = config.Megaraid {
-        info!("Created instance of Megaraid collector");
         let id = format!("megaraid#{}", "0");
+        let interval = parse_interval(&cfg.interval).map_err(|err| ConfigError::invalid("Megaraid", "interval", err))?
+            .or_else(|| cfg.tick_interval.map(|ti| Duration::from_secs(ti.max(1) * LEGACY_TICK_SEC)));
         let collector = Megaraid {
             id: id.clone(),
-            tick_interval: if let Some(ti) = cfg.tick_interval { ti } else { 1 },
+            interval: interval,
+            timeout: cfg.timeout,
             megacli_command: if let Some(ref cmd) = cfg.megacli_command { cmd.clone() } else { MEGA_DEFAULT_BINARY.to_string() },
             adapter: if let Some(adp) = cfg.adapter { adp.to_string() } else { MEGA_PARAM_ALL_ADAPTERS.to_string() },
+            tags: cfg.tags.clone(),
+            filter: compile_filter(&cfg.filter, "Megaraid.filter")?,
         };
-        vec![Box::new(collector)]
+        info!("Created instance of Megaraid collector");
+        Ok(vec![Box::new(collector)])
     } else {
-        Vec::new()
+        Ok(Vec::new())
     }
 }
 
@@ -115,8 +145,20 @@ impl Collector for Megaraid {
 
     fn shutdown(&mut self) {}
 
-    fn get_tick_interval(&self) -> i32 {
-        self.tick_interval
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_tags(&self) -> Option<&Tags> {
+        self.tags.as_ref()
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
+    fn get_timeout(&self) -> Option<u64> {
+        self.timeout
     }
 }
 
@@ -1,9 +1,13 @@
-use crate::bosun::{Metadata, Sample};
-use crate::config::Config;
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
+use crate::sanitizer;
+use crate::utils;
 
 use std::fmt;
 use std::error::Error as StdError;
 use std::num::{ParseIntError, ParseFloatError};
+use std::time::Duration;
 
 
 #[derive(Debug)]
@@ -46,41 +50,136 @@ pub trait Collector {
     fn metadata(&self) -> Vec<Metadata>;
     fn collect(&self) -> Result<Vec<Sample>, Error>;
     fn shutdown(&mut self);
-    fn get_tick_interval(&self) -> i32 { 1 }
+    // Time between two collections; `None` uses the global `CollectorInterval`.
+    fn get_interval(&self) -> Option<Duration> { None }
+    // Max number of seconds a collection may take; `None` uses the global `CollectorTimeout`.
+    fn get_timeout(&self) -> Option<u64> { None }
+    // Tags added to each sample of the collector; the global `Tags` take precedence.
+    fn get_tags(&self) -> Option<&Tags> { None }
+    // Rules to drop and rename the collector's metrics; applied before the global `Filter`.
+    fn get_filter(&self) -> Option<&MetricFilter> { None }
+    // Metadata for metrics that have been discovered while collecting and have not been returned by `metadata`.
+    fn pending_metadata(&self) -> Vec<Metadata> { Vec::new() }
 }
 
-pub fn create_collectors(config: &Config) -> Vec<Box<dyn Collector + Send>> {
+/// Creates the collectors of a config that has been checked by `Config::validate`; errors are only returned for configs
+/// that have not been validated.
+pub fn create_collectors(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
     let mut collectors = Vec::new();
 
     // Create Galera collector instances
-    let mut galeras = galera::create_instances(config);
+    let mut galeras = galera::create_instances(config)?;
     collectors.append(&mut galeras);
 
     // Create HasIpAddr collector instance
-    let mut hasipaddr = hasipaddr::create_instances(config);
+    let mut hasipaddr = hasipaddr::create_instances(config)?;
     collectors.append(&mut hasipaddr);
 
-    // Create Jvm collector instance
-    let mut jvm = jvm::create_instances(config);
+    // Create Jvm collector instances
+    let mut jvm = jvm::create_instances(config)?;
     collectors.append(&mut jvm);
 
     // Create Megaraid collector instances
-    let mut megaraids= megaraid::create_instances(config);
+    let mut megaraids= megaraid::create_instances(config)?;
     collectors.append(&mut megaraids);
 
     // Create Mongo collector instances
-    let mut mongo = mongo::create_instances(config);
+    let mut mongo = mongo::create_instances(config)?;
     collectors.append(&mut mongo);
 
     // Create Postfix collector instance
-    let mut postfix = postfix::create_instances(config);
+    let mut postfix = postfix::create_instances(config)?;
     collectors.append(&mut postfix);
 
+    // Create Relay collector instance
+    let mut relay = relay::create_instances(config)?;
+    collectors.append(&mut relay);
+
+    // Create Statsd collector instance
+    let mut statsd = statsd::create_instances(config)?;
+    collectors.append(&mut statsd);
+
     // Create internal rs-collector collector instance
     let mut rscollector = rscollector::create_instances(config);
     collectors.append(&mut rscollector);
 
-    collectors
+    Ok(collectors)
+}
+
+/// Returns a representation of the config a collector is created from, so that a reload can detect changed collectors.
+pub fn collector_config(config: &Config, id: &Id) -> String {
+    let settings = format!("{:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+                           config.CollectorTimeout,
+                           config.MaxCollectorTimeouts,
+                           config.MaxCollectorFailures,
+                           config.CollectorProbeInterval,
+                           config.CollectorInterval,
+                           config.AlignCollections,
+                           config.CollectorSplay);
+    let section = match section(id) {
+        "galera" => format!("{:?}", config.Galera),
+        "hasipaddr" => format!("{:?}", config.HasIpAddr),
+        "jvm" => format!("{:?}", jvm::find_config(config, id)),
+        "megaraid" => format!("{:?}", config.Megaraid),
+        "mongo" => format!("{:?}", mongo::find_config(config, id)),
+        "postfix" => format!("{:?}", config.Postfix),
+        "relay" => format!("{:?}", config.Relay),
+        "statsd" => format!("{:?}", config.Statsd),
+        _ => String::new(),
+    };
+
+    format!("{} {}", settings, section)
+}
+
+/// Parses the `Interval` of a collector config section, e.g., `30s` or `5m`.
+pub fn parse_interval(interval: &Option<String>) -> Result<Option<Duration>, String> {
+    match *interval {
+        Some(ref interval) => {
+            let interval = utils::parse_duration(interval)?;
+            if interval == Duration::from_secs(0) {
+                return Err("interval must be greater than zero".to_string());
+            }
+            Ok(Some(interval))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Checks the `Tags` of a collector config section against the characters allowed by OpenTSDB.
+pub fn validate_tags(tags: &Option<Tags>) -> Result<(), String> {
+    tags.as_ref().map_or(Ok(()), sanitizer::validate_tags)
+}
+
+/// Compiles the `Filter` of a collector config section.
+pub fn compile_filter(filter: &Option<FilterConfig>, section: &str) -> Result<Option<MetricFilter>, ConfigError> {
+    match *filter {
+        Some(ref filter) => MetricFilter::new(filter, section).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Creates a fresh instance of the collector with the given id, e.g., to replace a hung instance. Only the config
+/// section of the collector is instantiated, so other collectors are left alone.
+pub fn create_collector(config: &Config, id: &Id) -> Result<Option<Box<dyn Collector + Send>>, ConfigError> {
+    let collectors = match section(id) {
+        "galera" => galera::create_instances(config)?,
+        "hasipaddr" => hasipaddr::create_instances(config)?,
+        "jvm" => jvm::find_config(config, id).map(jvm::create_instance).into_iter().collect::<Result<_, _>>()?,
+        "megaraid" => megaraid::create_instances(config)?,
+        "mongo" => mongo::find_config(config, id).map(mongo::create_instance).into_iter().collect::<Result<_, _>>()?,
+        "postfix" => postfix::create_instances(config)?,
+        "relay" => relay::create_instances(config)?,
+        "statsd" => statsd::create_instances(config)?,
+        "rscollector" => rscollector::create_instances(config),
+        _ => Vec::new(),
+    };
+
+    Ok(collectors.into_iter().find(|c| c.id() == id))
+}
+
+/// Returns the config section of a collector id, e.g., `galera` for `galera#root@localhost`.
+fn section(id: &Id) -> &str {
+    id.split(|c| c == '#' || c == '@').next().unwrap_or("")
 }
 
 pub mod galera;
@@ -88,6 +187,65 @@ pub mod hasipaddr;
 pub mod jvm;
 pub mod mongo;
 pub mod postfix;
+pub mod relay;
 pub mod rscollector;
+pub mod statsd;
 pub mod megaraid;
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mongo::MongoConfig;
+
+    fn mongo_config(name: &str, port: u16) -> MongoConfig {
+        MongoConfig {
+            Name: name.to_string(), Host: "localhost".to_string(), Port: port, User: None, Password: None, PasswordFile: None,
+            UseSsl: None, CaCert: None, ClientCert: None, ClientCertKey: None, Timeout: None, Interval: None, Tags: None, Filter: None,
+        }
+    }
+
+    #[test]
+    fn collector_config_changes_only_for_changed_instance() {
+        let mut old_config = Config::default();
+        old_config.Mongo = vec![mongo_config("rs01", 27017), mongo_config("rs02", 27018)];
+        let mut new_config = Config::default();
+        new_config.Mongo = vec![mongo_config("rs01", 27017), mongo_config("rs02", 27018)];
+        new_config.Mongo[1].Timeout = Some(30);
+        let rs01 = "mongo#rs01#''@localhost:27017".to_string();
+        let rs02 = "mongo#rs02#''@localhost:27018".to_string();
+
+        assert_eq!(collector_config(&old_config, &rs01), collector_config(&new_config, &rs01));
+        assert!(collector_config(&old_config, &rs02) != collector_config(&new_config, &rs02));
+    }
+
+    #[test]
+    fn create_collector_of_single_mongo_instance() {
+        let mut config = Config::default();
+        config.Mongo = vec![mongo_config("rs01", 27017), mongo_config("rs02", 27018)];
+        let rs02 = "mongo#rs02#''@localhost:27018".to_string();
+
+        assert_eq!(create_collector(&config, &rs02).unwrap().map(|c| c.id().clone()), Some(rs02));
+        assert!(create_collector(&config, &"mongo#rs03#''@localhost:27019".to_string()).unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_interval_rejects_zero() {
+        assert_eq!(parse_interval(&Some("5m".to_string())), Ok(Some(Duration::from_secs(300))));
+        assert_eq!(parse_interval(&None), Ok(None));
+        assert!(parse_interval(&Some("0s".to_string())).is_err());
+        assert!(parse_interval(&Some("5 parsecs".to_string())).is_err());
+    }
+
+    #[test]
+    fn validate_tags_allows_opentsdb_characters_only() {
+        let tags = |key: &str, value: &str| Some(vec![(key.to_string(), value.to_string())].into_iter().collect());
+
+        assert_eq!(validate_tags(&None), Ok(()));
+        assert_eq!(validate_tags(&tags("cluster", "billing-eu_1.a/b")), Ok(()));
+        assert_eq!(validate_tags(&tags("stadt", "köln")), Ok(()));
+        assert!(validate_tags(&tags("cluster", "billing eu")).is_err());
+        assert!(validate_tags(&tags("clu$ter", "billing")).is_err());
+        assert!(validate_tags(&tags("cluster", "")).is_err());
+    }
+}
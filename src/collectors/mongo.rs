@@ -6,17 +6,21 @@
 //
 
 use crate::bosun::{Metadata, Rate, Sample, Tags};
-use crate::collectors::{Collector, Error, Id};
-use crate::config::Config;
+use crate::collectors::{compile_filter, parse_interval, validate_tags, Collector, Error, Id};
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
+use crate::utils;
 
 use chrono::prelude::*;
 use mongodb::{Bson, Document, Client, ClientOptions, CommandType, Error as MongodbError, ThreadedClient};
 use mongodb::db::{ThreadedDatabase};
 use std::error::Error as StdError;
 use std::f64;
+use std::time::Duration;
 
 #[derive(Debug)]
-#[derive(RustcDecodable)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
 pub struct MongoConfig {
     pub Name: String,
@@ -24,10 +28,50 @@ pub struct MongoConfig {
     pub Port: u16,
     pub User: Option<String>,
     pub Password: Option<String>,
+    pub PasswordFile: Option<String>,
     pub UseSsl: Option<bool>,
     pub CaCert: Option<String>,
     pub ClientCert: Option<String>,
     pub ClientCertKey: Option<String>,
+    pub Timeout: Option<u64>,
+    pub Interval: Option<String>,
+    pub Tags: Option<Tags>,
+    pub Filter: Option<FilterConfig>,
+}
+
+impl MongoConfig {
+    pub fn apply_defaults(&mut self) {
+        self.UseSsl.get_or_insert(false);
+    }
+
+    /// Reads `Password` from `PasswordFile`, if set.
+    pub fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        if let Some(ref path) = self.PasswordFile {
+            let section = format!("Mongo.{}", self.Name);
+            if self.Password.is_some() {
+                return Err(ConfigError::invalid(section, "PasswordFile", "must not be set together with Password"));
+            }
+            let password = utils::read_secret_file(path).map_err(|err| ConfigError::invalid(section, "PasswordFile", err))?;
+            self.Password = Some(password);
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let section = format!("Mongo.{}", self.Name);
+        if self.UseSsl.unwrap_or(false) && self.CaCert.is_none() {
+            return Err(ConfigError::invalid(section, "CaCert", "a CA cert is required, because UseSsl is set"));
+        }
+        if self.ClientCert.is_some() && self.ClientCertKey.is_none() {
+            return Err(ConfigError::invalid(section, "ClientCertKey", "a client key is required, because ClientCert is set"));
+        }
+        parse_interval(&self.Interval).map_err(|err| ConfigError::invalid(section.clone(), "Interval", err))?;
+        validate_tags(&self.Tags).map_err(|err| ConfigError::invalid(section.clone(), "Tags", err))?;
+        compile_filter(&self.Filter, &format!("{}.Filter", section))?;
+
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -36,40 +80,46 @@ pub struct Mongo {
     name: String,
     user: Option<String>,
     password: Option<String>,
-    use_ssl: bool,
     ca_cert: Option<String>,
     client_cert: Option<String>,
     client_cert_key: Option<String>,
     ip_or_hostname: String,
     port: u16,
     client: Option<Client>,
+    timeout: Option<u64>,
+    interval: Option<Duration>,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
 }
 
-pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
-    let mut collectors: Vec<Box<dyn Collector + Send>> = Vec::new();
-    for m in &config.Mongo {
-        let id = format!("mongo#{}#{}@{}:{}",
-                         m.Name, m.User.as_ref().unwrap_or(&"''".to_string()), m.Host, m.Port);
-        info!("Created instance of Mongo collector: {}", id);
-
-        let collector = Mongo {
-            id: id.clone(), name: m.Name.clone(), user: m.User.clone(), password: m.Password.clone(),
-            use_ssl: m.UseSsl.unwrap_or_else(|| false),
-            ca_cert: m.CaCert.clone(), client_cert: m.ClientCert.clone(), client_cert_key: m.ClientCertKey.clone(),
-            ip_or_hostname: m.Host.clone(), port: m.Port, client: None,
-        };
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
+    config.Mongo.iter().map(create_instance).collect()
+}
 
-        // TODO: This should be handled by the parser, but that requires serde
-        if collector.use_ssl && collector.ca_cert.is_none() {
-            error!("Failed to create instance of Mongo collector id='{}', because SSL is activated without CA cert", id);
-        } else if collector.client_cert.is_some() && collector.client_cert_key.is_none() {
-            error!("Failed to create instance of Mongo collector id='{}', because client cert is set without client key", id);
-        } else {
-            info!("Created instance of Galera collector: {}", id);
-            collectors.push(Box::new(collector));
-        }
-    }
-    collectors
+/// Creates the Mongo collector instance of a single `[[Mongo]]` section.
+pub fn create_instance(m: &MongoConfig) -> Result<Box<dyn Collector + Send>, ConfigError> {
+    let id = instance_id(m);
+    let section = format!("Mongo.{}", m.Name);
+    let collector = Mongo {
+        id: id.clone(), name: m.Name.clone(), user: m.User.clone(), password: m.Password.clone(),
+        ca_cert: m.CaCert.clone(), client_cert: m.ClientCert.clone(), client_cert_key: m.ClientCertKey.clone(),
+        ip_or_hostname: m.Host.clone(), port: m.Port, client: None, timeout: m.Timeout,
+        interval: parse_interval(&m.Interval).map_err(|err| ConfigError::invalid(section.clone(), "Interval", err))?,
+        tags: m.Tags.clone(),
+        filter: compile_filter(&m.Filter, &format!("{}.Filter", section))?,
+    };
+    info!("Created instance of Mongo collector: {}", id);
+    Ok(Box::new(collector))
+}
+
+fn instance_id(config: &MongoConfig) -> Id {
+    format!("mongo#{}#{}@{}:{}",
+            config.Name, config.User.as_ref().unwrap_or(&"''".to_string()), config.Host, config.Port)
+}
+
+/// Returns the config of the Mongo collector instance with the given id.
+pub fn find_config<'a>(config: &'a Config, id: &Id) -> Option<&'a MongoConfig> {
+    config.Mongo.iter().find(|m| &instance_id(m) == id)
 }
 
 impl Collector for Mongo {
@@ -125,6 +175,22 @@ impl Collector for Mongo {
         self.client = None;
     }
 
+    fn get_timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_tags(&self) -> Option<&Tags> {
+        self.tags.as_ref()
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
     fn metadata(&self) -> Vec<Metadata> {
         vec![
             Metadata::new( "mongo.connections.current", Rate::Gauge, "", "The number of incoming connections from clients to the database server . This number includes the current shell session. Consider the value of connections.available to add more context to this datum. The value will include all incoming connections including any shell connections or connections from other servers, such as replica set members or mongos instances." ),
@@ -1,34 +1,61 @@
 use crate::bosun::{Metadata, Rate, Sample, Tags};
 use crate::collectors::*;
-use crate::config::Config;
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
 
 use std::process::{Command, Output};
 use std::io::Result as IoResult;
+use std::time::Duration;
 
 static METRIC_NAME_QUEUES: &'static str = "postfix.queues";
 static POSTFIX_QUEUS: &'static [&'static str] = &["maildrop", "incoming", "hold", "active", "deferred"];
 
 #[derive(Debug)]
-#[derive(RustcDecodable)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
-pub struct PostfixConfig {}
+pub struct PostfixConfig {
+    pub Timeout: Option<u64>,
+    pub Interval: Option<String>,
+    pub Tags: Option<Tags>,
+    pub Filter: Option<FilterConfig>,
+}
+
+impl PostfixConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        parse_interval(&self.Interval).map_err(|err| ConfigError::invalid("Postfix", "Interval", err))?;
+        validate_tags(&self.Tags).map_err(|err| ConfigError::invalid("Postfix", "Tags", err))?;
+        compile_filter(&self.Filter, "Postfix.Filter")?;
+
+        Ok(())
+    }
+}
 
 #[derive(Clone)]
 pub struct Postfix {
     id: Id,
+    timeout: Option<u64>,
+    interval: Option<Duration>,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
 }
 
-pub fn create_instances(config: &Config) -> Vec<Box<dyn Collector + Send>> {
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
     match config.Postfix {
-        Some(_) => {
+        Some(ref config) => {
             let id = "postfix".to_string();
+            let collector = Postfix {
+                id: id.clone(),
+                timeout: config.Timeout,
+                interval: parse_interval(&config.Interval).map_err(|err| ConfigError::invalid("Postfix", "Interval", err))?,
+                tags: config.Tags.clone(),
+                filter: compile_filter(&config.Filter, "Postfix.Filter")?,
+            };
             info!("Created instance of Postfix collector: {}", id);
-
-            let collector = Postfix { id: id };
-            vec![Box::new(collector)]
+            Ok(vec![Box::new(collector)])
         },
         None => {
-            Vec::new()
+            Ok(Vec::new())
         }
     }
 }
@@ -54,6 +81,22 @@ impl Collector for Postfix {
 
     fn shutdown(&mut self) {}
 
+    fn get_timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_tags(&self) -> Option<&Tags> {
+        self.tags.as_ref()
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
     fn metadata(&self) -> Vec<Metadata> {
         vec![
             Metadata::new(format!("{}.maildrop", METRIC_NAME_QUEUES),
@@ -0,0 +1,335 @@
+// See http://opentsdb.net/docs/build/html/api_http/put.html
+
+use crate::bosun::{Metadata, Rate, Sample, Tags};
+use crate::collectors::*;
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
+
+use flate2::read::GzDecoder;
+use serde_json::{self, Value};
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tiny_http::{Request, Response, Server};
+
+static PUT_PATH: &'static str = "/api/put";
+static DEFAULT_MAX_BUFFER_SIZE: usize = 100000;
+static MAX_BODY_SIZE: u64 = 16 * 1024 * 1024;
+static RECV_TIMEOUT_MS: u64 = 500;
+// Timestamps larger than this are in milliseconds; OpenTSDB accepts both.
+static MAX_TIMESTAMP_SEC: u64 = 9999999999;
+static RELAY_DATAPOINTS_METRIC_NAME: &'static str = "rs-collector.relay.datapoints";
+static RELAY_ERRORS_METRIC_NAME: &'static str = "rs-collector.relay.errors";
+static RELAY_DROPPED_METRIC_NAME: &'static str = "rs-collector.relay.dropped";
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct RelayConfig {
+    /// Address and port to accept `/api/put` requests on, e.g., `127.0.0.1:4242`
+    pub Listen: String,
+    /// Max number of datapoints to buffer between two collections; defaults to 100000
+    pub MaxBufferSize: Option<usize>,
+    /// Time between two collections, e.g., `30s`; defaults to `CollectorInterval`
+    pub Interval: Option<String>,
+    pub Tags: Option<Tags>,
+    pub Filter: Option<FilterConfig>,
+}
+
+impl RelayConfig {
+    pub fn apply_defaults(&mut self) {
+        self.MaxBufferSize.get_or_insert(DEFAULT_MAX_BUFFER_SIZE);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.MaxBufferSize == Some(0) {
+            return Err(ConfigError::invalid("Relay", "MaxBufferSize", "must be greater than zero"));
+        }
+        parse_interval(&self.Interval).map_err(|err| ConfigError::invalid("Relay", "Interval", err))?;
+        validate_tags(&self.Tags).map_err(|err| ConfigError::invalid("Relay", "Tags", err))?;
+        compile_filter(&self.Filter, "Relay.Filter")?;
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Buffer {
+    samples: Vec<Sample>,
+    datapoints: u64,
+    errors: u64,
+    dropped: u64,
+}
+
+/// Accepts Bosun/OpenTSDB `/api/put` requests from local applications and passes the datapoints on like samples of
+/// any other collector.
+pub struct Relay {
+    id: Id,
+    listen: String,
+    max_buffer_size: usize,
+    buffer: Arc<Mutex<Buffer>>,
+    shutdown: Arc<AtomicBool>,
+    server_thread: Option<JoinHandle<()>>,
+    interval: Option<Duration>,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
+    match config.Relay {
+        Some(ref config) => {
+            let id = format!("relay@{}", config.Listen);
+            let collector = Relay {
+                id: id.clone(),
+                listen: config.Listen.clone(),
+                max_buffer_size: config.MaxBufferSize.unwrap_or(DEFAULT_MAX_BUFFER_SIZE),
+                buffer: Arc::new(Mutex::new(Buffer::default())),
+                shutdown: Arc::new(AtomicBool::new(false)),
+                server_thread: None,
+                interval: parse_interval(&config.Interval).map_err(|err| ConfigError::invalid("Relay", "Interval", err))?,
+                tags: config.Tags.clone(),
+                filter: compile_filter(&config.Filter, "Relay.Filter")?,
+            };
+            info!("Created instance of Relay collector: {}", id);
+            Ok(vec![Box::new(collector)])
+        }
+        None => {
+            Ok(Vec::new())
+        }
+    }
+}
+
+impl Collector for Relay {
+    fn init(&mut self) -> Result<(), Box<Error>> {
+        if self.server_thread.is_some() {
+            return Ok(());
+        }
+
+        let server = Server::http(self.listen.as_str())
+            .map_err(|e| Box::new(Error::InitError(format!("failed to listen on {}: {}", self.listen, e))))?;
+        self.shutdown.store(false, Ordering::Relaxed);
+        let buffer = self.buffer.clone();
+        let shutdown = self.shutdown.clone();
+        let max_buffer_size = self.max_buffer_size;
+        self.server_thread = Some(thread::spawn(move || serve(server, buffer, shutdown, max_buffer_size)));
+
+        Ok(())
+    }
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn collect(&self) -> Result<Vec<Sample>, Error> {
+        if self.server_thread.is_none() {
+            // Makes the scheduler re-initialize the relay, which tries to listen again.
+            return Err(Error::CollectionError(format!("relay is not listening on {}", self.listen)));
+        }
+
+        let mut buffer = self.buffer.lock().unwrap();
+        let mut samples: Vec<Sample> = buffer.samples.drain(..).collect();
+        debug!("Relaying {} datapoints.", samples.len());
+
+        samples.push(Sample::new(RELAY_DATAPOINTS_METRIC_NAME, buffer.datapoints as f64));
+        samples.push(Sample::new(RELAY_ERRORS_METRIC_NAME, buffer.errors as f64));
+        samples.push(Sample::new(RELAY_DROPPED_METRIC_NAME, buffer.dropped as f64));
+
+        Ok(samples)
+    }
+
+    fn shutdown(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(server_thread) = self.server_thread.take() {
+            let _ = server_thread.join();
+        }
+    }
+
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_tags(&self) -> Option<&Tags> {
+        self.tags.as_ref()
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
+    fn metadata(&self) -> Vec<Metadata> {
+        vec![
+            Metadata::new(RELAY_DATAPOINTS_METRIC_NAME,
+                          Rate::Counter,
+                          "Datapoints",
+                          "Shows the number of datapoints received by the relay."),
+            Metadata::new(RELAY_ERRORS_METRIC_NAME,
+                          Rate::Counter,
+                          "Datapoints",
+                          "Shows the number of invalid datapoints rejected by the relay."),
+            Metadata::new(RELAY_DROPPED_METRIC_NAME,
+                          Rate::Counter,
+                          "Datapoints",
+                          "Shows the number of datapoints dropped by the relay, because its buffer was full."),
+        ]
+    }
+}
+
+fn serve(server: Server, buffer: Arc<Mutex<Buffer>>, shutdown: Arc<AtomicBool>, max_buffer_size: usize) {
+    info!("Relay thread started; listening on {}.", server.server_addr());
+    while !shutdown.load(Ordering::Relaxed) {
+        let mut request = match server.recv_timeout(Duration::from_millis(RECV_TIMEOUT_MS)) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(err) => {
+                error!("Relay failed to receive request, because {}.", err);
+                continue;
+            }
+        };
+
+        let path = request.url().split('?').next().unwrap_or("").to_string();
+        let response = if path != PUT_PATH {
+            Response::from_string("Not Found").with_status_code(404)
+        } else {
+            match read_body(&mut request) {
+                Ok(body) => {
+                    let (samples, errors) = parse_datapoints(&body);
+                    let mut buffer = buffer.lock().unwrap();
+                    buffer.datapoints += samples.len() as u64;
+                    buffer.errors += errors.len() as u64;
+                    for s in samples {
+                        if buffer.samples.len() < max_buffer_size {
+                            buffer.samples.push(s);
+                        } else {
+                            buffer.dropped += 1;
+                        }
+                    }
+                    if errors.is_empty() {
+                        Response::from_string("").with_status_code(204)
+                    } else {
+                        debug!("Relay rejected {} datapoints: {:?}", errors.len(), errors);
+                        Response::from_string(errors.join("\n")).with_status_code(400)
+                    }
+                }
+                Err(err) => Response::from_string(err).with_status_code(400),
+            }
+        };
+        if let Err(err) = request.respond(response) {
+            debug!("Relay failed to respond, because {}.", err);
+        }
+    }
+    info!("Relay thread finished.");
+}
+
+fn read_body(request: &mut Request) -> Result<String, String> {
+    let gzipped = request.headers().iter()
+        .any(|h| h.field.equiv("Content-Encoding") && h.value.as_str().eq_ignore_ascii_case("gzip"));
+    let reader = request.as_reader().take(MAX_BODY_SIZE);
+
+    let mut body = String::new();
+    let result = if gzipped {
+        GzDecoder::new(reader).read_to_string(&mut body)
+    } else {
+        let mut reader = reader;
+        reader.read_to_string(&mut body)
+    };
+    result.map_err(|e| format!("failed to read request body: {}", e))?;
+
+    Ok(body)
+}
+
+/// Parses a single datapoint or an array of datapoints; returns all valid datapoints as samples and an error message
+/// for each invalid one.
+fn parse_datapoints(body: &str) -> (Vec<Sample>, Vec<String>) {
+    let json: Value = match serde_json::from_str(body) {
+        Ok(json) => json,
+        Err(err) => return (Vec::new(), vec![format!("invalid JSON: {}", err)]),
+    };
+    let datapoints = match json {
+        Value::Array(datapoints) => datapoints,
+        datapoint => vec![datapoint],
+    };
+
+    let mut samples = Vec::new();
+    let mut errors = Vec::new();
+    for d in &datapoints {
+        match parse_datapoint(d) {
+            Ok(sample) => samples.push(sample),
+            Err(err) => errors.push(format!("invalid datapoint {}: {}", d, err)),
+        }
+    }
+
+    (samples, errors)
+}
+
+fn parse_datapoint(json: &Value) -> Result<Sample, String> {
+    let metric = json.get("metric")
+        .and_then(|m| m.as_str())
+        .filter(|m| !m.is_empty())
+        .ok_or_else(|| "missing metric".to_string())?;
+    let value = match json.get("value") {
+        Some(Value::String(s)) => s.parse::<f64>().map_err(|_| format!("invalid value '{}'", s))?,
+        Some(v) => v.as_f64().ok_or_else(|| "invalid value".to_string())?,
+        None => return Err("missing value".to_string()),
+    };
+    let time = match json.get("timestamp") {
+        Some(t) => t.as_u64().ok_or_else(|| "invalid timestamp".to_string())?,
+        None => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+    };
+    let time = if time > MAX_TIMESTAMP_SEC { time / 1000 } else { time };
+
+    let mut tags = Tags::new();
+    if let Some(json_tags) = json.get("tags") {
+        let json_tags = json_tags.as_object().ok_or_else(|| "invalid tags".to_string())?;
+        for (k, v) in json_tags {
+            let v = match *v {
+                Value::String(ref s) => s.clone(),
+                Value::Number(_) | Value::Bool(_) => v.to_string(),
+                _ => return Err(format!("invalid value of tag '{}'", k)),
+            };
+            tags.insert(k.clone(), v);
+        }
+    }
+
+    Ok(Sample { time, metric: metric.to_string(), value, tags })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_single_datapoint() {
+        let (samples, errors) = parse_datapoints(
+            r#"{"metric": "cron.backup.duration", "timestamp": 1500000000000, "value": "42.5", "tags": {"job": "backup"}}"#);
+
+        assert!(errors.is_empty());
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].metric, "cron.backup.duration");
+        assert_eq!(samples[0].time, 1500000000);
+        assert_eq!(samples[0].value, 42.5);
+        assert_eq!(samples[0].tags["job"], "backup");
+    }
+
+    #[test]
+    fn parse_array_with_invalid_datapoints() {
+        let (samples, errors) = parse_datapoints(
+            r#"[{"metric": "a", "timestamp": 10, "value": 1}, {"timestamp": 10, "value": 1}, {"metric": "b", "value": "x"}]"#);
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].metric, "a");
+        assert_eq!(samples[0].time, 10);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn parse_invalid_json() {
+        let (samples, errors) = parse_datapoints("not json");
+
+        assert!(samples.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+}
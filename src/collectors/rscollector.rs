@@ -7,10 +7,27 @@ use std::num::ParseFloatError;
 use procinfo::pid;
 
 pub static RS_COLLECTOR_STATS_SAMPLES_METRICNAME: &'static str = "rs-collector.stats.samples";
+pub static RS_COLLECTOR_SPOOL_SPOOLED_METRICNAME: &'static str = "rs-collector.spool.spooled";
+pub static RS_COLLECTOR_SPOOL_REPLAYED_METRICNAME: &'static str = "rs-collector.spool.replayed";
+pub static RS_COLLECTOR_SPOOL_DROPPED_METRICNAME: &'static str = "rs-collector.spool.dropped";
+pub static RS_COLLECTOR_SPOOL_SIZE_METRICNAME: &'static str = "rs-collector.spool.size";
+pub static RS_COLLECTOR_BOSUN_SEND_ERRORS_METRICNAME: &'static str = "rs-collector.bosun.send_errors";
+pub static RS_COLLECTOR_BOSUN_CIRCUIT_STATE_METRICNAME: &'static str = "rs-collector.bosun.circuit_state";
+pub static RS_COLLECTOR_COLLECTOR_TIMEOUTS_METRICNAME: &'static str = "rs-collector.collector.timeouts";
+pub static RS_COLLECTOR_COLLECTOR_STATE_METRICNAME: &'static str = "rs-collector.collector.state";
+pub static RS_COLLECTOR_COLLECTOR_DURATION_METRICNAME: &'static str = "rs-collector.collector.duration";
+pub static RS_COLLECTOR_COLLECTOR_SAMPLES_METRICNAME: &'static str = "rs-collector.collector.samples";
+pub static RS_COLLECTOR_COLLECTOR_ERRORS_METRICNAME: &'static str = "rs-collector.collector.errors";
+pub static RS_COLLECTOR_COLLECTOR_LAST_SUCCESS_METRICNAME: &'static str = "rs-collector.collector.last_success";
+pub static RS_COLLECTOR_COLLECTOR_INIT_FAILURES_METRICNAME: &'static str = "rs-collector.collector.init_failures";
+pub static RS_COLLECTOR_COLLECTOR_SANITIZED_SAMPLES_METRICNAME: &'static str = "rs-collector.collector.sanitized_samples";
+pub static RS_COLLECTOR_COLLECTOR_REJECTED_SAMPLES_METRICNAME: &'static str = "rs-collector.collector.rejected_samples";
+pub static RS_COLLECTOR_COLLECTOR_REJECTED_METADATA_METRICNAME: &'static str = "rs-collector.collector.rejected_metadata";
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 #[derive(Debug)]
-#[derive(RustcDecodable)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
 pub struct RsCollectorConfig {}
 
@@ -60,6 +77,73 @@ impl Collector for RsCollector {
                           Rate::Gauge,
                           "Samples",
                           "Shows the number of transmitted samples."),
+            // The spool values are computed and send in the Bosun module directly, if the spool is enabled.
+            Metadata::new(RS_COLLECTOR_SPOOL_SPOOLED_METRICNAME,
+                          Rate::Counter,
+                          "Samples",
+                          "Shows the number of samples written to the spool, because they could not be sent to Bosun."),
+            Metadata::new(RS_COLLECTOR_SPOOL_REPLAYED_METRICNAME,
+                          Rate::Counter,
+                          "Samples",
+                          "Shows the number of spooled samples that have been sent to Bosun."),
+            Metadata::new(RS_COLLECTOR_SPOOL_DROPPED_METRICNAME,
+                          Rate::Counter,
+                          "Samples",
+                          "Shows the number of spooled samples that have been dropped, because the spool exceeded its max size or could not be written."),
+            Metadata::new(RS_COLLECTOR_SPOOL_SIZE_METRICNAME,
+                          Rate::Gauge,
+                          "Bytes",
+                          "Shows the size of the spool on disk."),
+            // The Bosun sender values are computed and send in the Bosun module directly.
+            Metadata::new(RS_COLLECTOR_BOSUN_SEND_ERRORS_METRICNAME,
+                          Rate::Counter,
+                          "Requests",
+                          "Shows the number of requests to Bosun that failed or have been rejected."),
+            Metadata::new(RS_COLLECTOR_BOSUN_CIRCUIT_STATE_METRICNAME,
+                          Rate::Gauge,
+                          "State",
+                          "Shows the state of the circuit breaker guarding requests to Bosun. [0 = Healthy, 1 = Degraded, 2 = Open]"),
+            // The collector values are computed and send by the scheduler directly for each collector.
+            Metadata::new(RS_COLLECTOR_COLLECTOR_TIMEOUTS_METRICNAME,
+                          Rate::Counter,
+                          "Collections",
+                          "Shows the number of collections that exceeded the collector's timeout."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_STATE_METRICNAME,
+                          Rate::Gauge,
+                          "State",
+                          "Shows the state of the collector. [0 = Healthy, 1 = Failing, 2 = Quarantined]"),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_DURATION_METRICNAME,
+                          Rate::Gauge,
+                          "Milliseconds",
+                          "Shows the duration of the collector's last collection."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_SAMPLES_METRICNAME,
+                          Rate::Gauge,
+                          "Samples",
+                          "Shows the number of samples produced by the collector's last successful collection."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_ERRORS_METRICNAME,
+                          Rate::Counter,
+                          "Collections",
+                          "Shows the number of failed collections of the collector."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_LAST_SUCCESS_METRICNAME,
+                          Rate::Gauge,
+                          "Timestamp",
+                          "Shows the Unix timestamp of the collector's last successful collection."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_INIT_FAILURES_METRICNAME,
+                          Rate::Counter,
+                          "Inits",
+                          "Shows the number of failed initializations of the collector."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_SANITIZED_SAMPLES_METRICNAME,
+                          Rate::Counter,
+                          "Samples",
+                          "Shows the number of samples of the collector with invalid metric names or tags that have been sanitized."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_REJECTED_SAMPLES_METRICNAME,
+                          Rate::Counter,
+                          "Samples",
+                          "Shows the number of samples of the collector with invalid metric names or tags that have been dropped."),
+            Metadata::new(RS_COLLECTOR_COLLECTOR_REJECTED_METADATA_METRICNAME,
+                          Rate::Counter,
+                          "Metadata",
+                          "Shows the number of metadata of the collector with invalid metric names that have been dropped."),
         ]
     }
 }
@@ -0,0 +1,485 @@
+// See https://github.com/statsd/statsd/blob/master/docs/metric_types.md
+
+use crate::bosun::{Metadata, Rate, Sample, Tags};
+use crate::collectors::*;
+use crate::config::{Config, ConfigError};
+use crate::filter::{FilterConfig, MetricFilter};
+use crate::utils;
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, Instant};
+
+static DEFAULT_LISTEN: &'static str = "127.0.0.1:8125";
+static DEFAULT_PERCENTILES: &'static [u32] = &[50, 90, 99];
+static DEFAULT_GAUGE_EXPIRY: &'static str = "5m";
+static MAX_PACKET_SIZE: usize = 65535;
+static RECV_TIMEOUT_MS: u64 = 500;
+static STATSD_PACKETS_METRIC_NAME: &'static str = "rs-collector.statsd.packets";
+static STATSD_ERRORS_METRIC_NAME: &'static str = "rs-collector.statsd.errors";
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct StatsdConfig {
+    /// Address and port to receive StatsD packets on; defaults to `127.0.0.1:8125`
+    pub Listen: Option<String>,
+    /// Percentiles to compute for timers; defaults to [50, 90, 99]
+    pub Percentiles: Option<Vec<u32>>,
+    /// Time after which a gauge that has not been updated is no longer reported, e.g., `1h`; defaults to `5m`
+    pub GaugeExpiry: Option<String>,
+    /// Time between two collections and thus the flush interval, e.g., `10s`; defaults to `CollectorInterval`
+    pub Interval: Option<String>,
+    pub Tags: Option<Tags>,
+    pub Filter: Option<FilterConfig>,
+}
+
+impl StatsdConfig {
+    pub fn apply_defaults(&mut self) {
+        self.Listen.get_or_insert_with(|| DEFAULT_LISTEN.to_string());
+        self.Percentiles.get_or_insert_with(|| DEFAULT_PERCENTILES.to_vec());
+        self.GaugeExpiry.get_or_insert_with(|| DEFAULT_GAUGE_EXPIRY.to_string());
+    }
+
+    fn gauge_expiry(&self) -> Result<Duration, ConfigError> {
+        let gauge_expiry = self.GaugeExpiry.as_ref().map_or(DEFAULT_GAUGE_EXPIRY, |e| e.as_str());
+        match utils::parse_duration(gauge_expiry) {
+            Ok(expiry) if expiry == Duration::from_secs(0) =>
+                Err(ConfigError::invalid("Statsd", "GaugeExpiry", "expiry must be greater than zero".to_string())),
+            Ok(expiry) => Ok(expiry),
+            Err(err) => Err(ConfigError::invalid("Statsd", "GaugeExpiry", err)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(p) = self.Percentiles.iter().flatten().find(|p| **p == 0 || **p > 100) {
+            return Err(ConfigError::invalid("Statsd", "Percentiles", format!("{} is not between 1 and 100", p)));
+        }
+        parse_interval(&self.Interval).map_err(|err| ConfigError::invalid("Statsd", "Interval", err))?;
+        self.gauge_expiry()?;
+        validate_tags(&self.Tags).map_err(|err| ConfigError::invalid("Statsd", "Tags", err))?;
+        compile_filter(&self.Filter, "Statsd.Filter")?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Counter(f64),
+    Gauge(f64),
+    GaugeDelta(f64),
+    /// Value and sample rate
+    Timer(f64, f64),
+    Set(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Line {
+    name: String,
+    tags: Vec<(String, String)>,
+    value: Value,
+}
+
+type Key = (String, Vec<(String, String)>);
+
+/// Kind of a sample derived from a StatsD series, which determines its metadata
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stat {
+    CounterCount,
+    CounterRate,
+    Gauge,
+    TimerCount,
+    TimerStatistic,
+    TimerPercentile,
+    SetUnique,
+}
+
+impl Stat {
+    fn metadata(self, metric: &str) -> Metadata {
+        let (rate, unit, description) = match self {
+            Stat::CounterCount => (Rate::Gauge, "", "Number of StatsD counter increments per flush interval."),
+            Stat::CounterRate => (Rate::Rate, "per second", "StatsD counter as per second rate."),
+            Stat::Gauge => (Rate::Gauge, "", "StatsD gauge."),
+            Stat::TimerCount => (Rate::Gauge, "", "Number of StatsD timer values per flush interval."),
+            Stat::TimerStatistic => (Rate::Gauge, "ms", "StatsD timer statistic per flush interval."),
+            Stat::TimerPercentile => (Rate::Gauge, "ms", "StatsD timer percentile per flush interval."),
+            Stat::SetUnique => (Rate::Gauge, "", "Number of unique StatsD set values per flush interval."),
+        };
+
+        Metadata::new(metric, rate, unit, description)
+    }
+}
+
+/// Aggregates received values per flush interval; gauges keep their last value across intervals until they expire.
+struct Aggregator {
+    counters: HashMap<Key, f64>,
+    gauges: HashMap<Key, Gauge>,
+    timers: HashMap<Key, Timer>,
+    sets: HashMap<Key, HashSet<String>>,
+    packets: u64,
+    errors: u64,
+    last_flush: Instant,
+    announced: HashSet<String>,
+    pending_metadata: Vec<Metadata>,
+}
+
+struct Gauge {
+    value: f64,
+    updated: Instant,
+}
+
+/// Values of a timer and the number of values it stands for according to their sample rates.
+#[derive(Default)]
+struct Timer {
+    values: Vec<f64>,
+    count: f64,
+}
+
+/// Receives StatsD counters, gauges, timers, and sets via UDP and aggregates them into samples on every collection.
+pub struct Statsd {
+    id: Id,
+    listen: String,
+    percentiles: Vec<u32>,
+    gauge_expiry: Duration,
+    aggregator: Arc<Mutex<Aggregator>>,
+    shutdown: Arc<AtomicBool>,
+    listener_thread: Option<JoinHandle<()>>,
+    interval: Option<Duration>,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Collector + Send>>, ConfigError> {
+    match config.Statsd {
+        Some(ref config) => {
+            let listen = config.Listen.clone().unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+            let id = format!("statsd@{}", listen);
+            let collector = Statsd {
+                id: id.clone(),
+                listen: listen,
+                percentiles: config.Percentiles.clone().unwrap_or_else(|| DEFAULT_PERCENTILES.to_vec()),
+                gauge_expiry: config.gauge_expiry()?,
+                aggregator: Arc::new(Mutex::new(Aggregator::new(Instant::now()))),
+                shutdown: Arc::new(AtomicBool::new(false)),
+                listener_thread: None,
+                interval: parse_interval(&config.Interval).map_err(|err| ConfigError::invalid("Statsd", "Interval", err))?,
+                tags: config.Tags.clone(),
+                filter: compile_filter(&config.Filter, "Statsd.Filter")?,
+            };
+            info!("Created instance of Statsd collector: {}", id);
+            Ok(vec![Box::new(collector)])
+        }
+        None => {
+            Ok(Vec::new())
+        }
+    }
+}
+
+impl Collector for Statsd {
+    fn init(&mut self) -> Result<(), Box<Error>> {
+        if self.listener_thread.is_some() {
+            return Ok(());
+        }
+
+        let socket = UdpSocket::bind(self.listen.as_str())
+            .and_then(|s| s.set_read_timeout(Some(Duration::from_millis(RECV_TIMEOUT_MS))).map(|_| s))
+            .map_err(|e| Box::new(Error::InitError(format!("failed to listen on {}: {}", self.listen, e))))?;
+        self.shutdown.store(false, Ordering::Relaxed);
+        let aggregator = self.aggregator.clone();
+        let shutdown = self.shutdown.clone();
+        self.listener_thread = Some(thread::spawn(move || receive(socket, aggregator, shutdown)));
+
+        Ok(())
+    }
+
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn collect(&self) -> Result<Vec<Sample>, Error> {
+        if self.listener_thread.is_none() {
+            // Makes the scheduler re-initialize the collector, which tries to listen again.
+            return Err(Error::CollectionError(format!("statsd is not listening on {}", self.listen)));
+        }
+
+        let mut aggregator = self.aggregator.lock().unwrap();
+        let samples = aggregator.flush(Instant::now(), &self.percentiles, self.gauge_expiry);
+        debug!("Aggregated {} StatsD samples.", samples.len());
+
+        Ok(samples)
+    }
+
+    fn shutdown(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(listener_thread) = self.listener_thread.take() {
+            let _ = listener_thread.join();
+        }
+    }
+
+    fn get_interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    fn get_tags(&self) -> Option<&Tags> {
+        self.tags.as_ref()
+    }
+
+    fn get_filter(&self) -> Option<&MetricFilter> {
+        self.filter.as_ref()
+    }
+
+    fn metadata(&self) -> Vec<Metadata> {
+        vec![
+            Metadata::new(STATSD_PACKETS_METRIC_NAME,
+                          Rate::Counter,
+                          "Packets",
+                          "Shows the number of StatsD packets received."),
+            Metadata::new(STATSD_ERRORS_METRIC_NAME,
+                          Rate::Counter,
+                          "Lines",
+                          "Shows the number of invalid StatsD lines."),
+        ]
+    }
+
+    fn pending_metadata(&self) -> Vec<Metadata> {
+        let mut aggregator = self.aggregator.lock().unwrap();
+        aggregator.pending_metadata.drain(..).collect()
+    }
+}
+
+fn receive(socket: UdpSocket, aggregator: Arc<Mutex<Aggregator>>, shutdown: Arc<AtomicBool>) {
+    info!("Statsd thread started; listening on {:?}.", socket.local_addr());
+    let mut buf = vec![0u8; MAX_PACKET_SIZE];
+    while !shutdown.load(Ordering::Relaxed) {
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, _)) => len,
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => continue,
+            Err(err) => {
+                error!("Statsd failed to receive packet, because {}.", err);
+                continue;
+            }
+        };
+        let packet = String::from_utf8_lossy(&buf[..len]);
+        let now = Instant::now();
+
+        let mut aggregator = aggregator.lock().unwrap();
+        aggregator.packets += 1;
+        for line in packet.lines().filter(|l| !l.trim().is_empty()) {
+            match parse_line(line) {
+                Ok(line) => aggregator.add(line, now),
+                Err(err) => {
+                    debug!("Statsd received invalid line '{}', because {}.", line, err);
+                    aggregator.errors += 1;
+                }
+            }
+        }
+    }
+    info!("Statsd thread finished.");
+}
+
+/// Parses `<name>:<value>|<type>[|@<sample rate>][|#<tag>:<value>,...]`; the tags follow DogStatsD's extension.
+fn parse_line(line: &str) -> Result<Line, String> {
+    let colon = line.find(':').ok_or_else(|| "missing ':'".to_string())?;
+    let name = line[..colon].trim();
+    if name.is_empty() {
+        return Err("missing name".to_string());
+    }
+    let mut fields = line[colon + 1..].split('|');
+    let raw_value = fields.next().unwrap_or("").trim();
+    let kind = fields.next().ok_or_else(|| "missing type".to_string())?.trim();
+
+    let mut sample_rate = 1f64;
+    let mut tags = Vec::new();
+    for field in fields {
+        if let Some(rate) = field.strip_prefix('@') {
+            sample_rate = rate.parse::<f64>().map_err(|_| format!("invalid sample rate '{}'", field))?;
+            if sample_rate <= 0f64 || sample_rate > 1f64 {
+                return Err(format!("invalid sample rate '{}'", field));
+            }
+        } else if let Some(field_tags) = field.strip_prefix('#') {
+            for tag in field_tags.split(',').filter(|t| !t.is_empty()) {
+                let mut kv = tag.splitn(2, ':');
+                let k = kv.next().unwrap_or("");
+                let v = kv.next().unwrap_or("");
+                tags.push((k.to_string(), v.to_string()));
+            }
+        }
+    }
+    tags.sort();
+
+    let parse_value = || raw_value.parse::<f64>().map_err(|_| format!("invalid value '{}'", raw_value));
+    let value = match kind {
+        "c" => Value::Counter(parse_value()? / sample_rate),
+        "g" if raw_value.starts_with('+') || raw_value.starts_with('-') => Value::GaugeDelta(parse_value()?),
+        "g" => Value::Gauge(parse_value()?),
+        "ms" | "h" => Value::Timer(parse_value()?, sample_rate),
+        "s" => Value::Set(raw_value.to_string()),
+        _ => return Err(format!("unknown type '{}'", kind)),
+    };
+
+    Ok(Line { name: name.to_string(), tags, value })
+}
+
+impl Aggregator {
+    fn new(now: Instant) -> Aggregator {
+        Aggregator {
+            counters: HashMap::new(),
+            gauges: HashMap::new(),
+            timers: HashMap::new(),
+            sets: HashMap::new(),
+            packets: 0,
+            errors: 0,
+            last_flush: now,
+            announced: HashSet::new(),
+            pending_metadata: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, line: Line, now: Instant) {
+        let key = (line.name, line.tags);
+        match line.value {
+            Value::Counter(v) => *self.counters.entry(key).or_insert(0f64) += v,
+            Value::Gauge(v) => { self.gauges.insert(key, Gauge { value: v, updated: now }); },
+            Value::GaugeDelta(v) => {
+                let gauge = self.gauges.entry(key).or_insert(Gauge { value: 0f64, updated: now });
+                gauge.value += v;
+                gauge.updated = now;
+            }
+            Value::Timer(v, sample_rate) => {
+                let timer = self.timers.entry(key).or_insert_with(Timer::default);
+                timer.values.push(v);
+                timer.count += 1f64 / sample_rate;
+            }
+            Value::Set(v) => { self.sets.entry(key).or_insert_with(HashSet::new).insert(v); },
+        }
+    }
+
+    /// Returns the aggregated samples of the interval since the last flush and resets counters, timers, and sets.
+    /// Counters become per second rates, timers count, mean, min, max, and percentiles. Gauges that have not been
+    /// updated within `gauge_expiry` are removed.
+    fn flush(&mut self, now: Instant, percentiles: &[u32], gauge_expiry: Duration) -> Vec<Sample> {
+        let elapsed = now.duration_since(self.last_flush);
+        let interval = (elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9).max(1f64);
+        self.last_flush = now;
+
+        let mut stats = Vec::new();
+        for ((name, tags), count) in self.counters.drain() {
+            stats.push((Stat::CounterCount, sample(&format!("{}.count", name), count, &tags)));
+            stats.push((Stat::CounterRate, sample(&format!("{}.rate", name), count / interval, &tags)));
+        }
+        self.gauges.retain(|_, gauge| now.duration_since(gauge.updated) < gauge_expiry);
+        for ((name, tags), gauge) in &self.gauges {
+            stats.push((Stat::Gauge, sample(name, gauge.value, tags)));
+        }
+        for ((name, tags), timer) in self.timers.drain() {
+            let mut values = timer.values;
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+            let sum: f64 = values.iter().sum();
+            stats.push((Stat::TimerCount, sample(&format!("{}.count", name), timer.count, &tags)));
+            stats.push((Stat::TimerStatistic, sample(&format!("{}.mean", name), sum / values.len() as f64, &tags)));
+            stats.push((Stat::TimerStatistic, sample(&format!("{}.min", name), values[0], &tags)));
+            stats.push((Stat::TimerStatistic, sample(&format!("{}.max", name), values[values.len() - 1], &tags)));
+            for p in percentiles {
+                stats.push((Stat::TimerPercentile, sample(&format!("{}.p{}", name, p), percentile(&values, *p), &tags)));
+            }
+        }
+        for ((name, tags), values) in self.sets.drain() {
+            stats.push((Stat::SetUnique, sample(&format!("{}.unique", name), values.len() as f64, &tags)));
+        }
+
+        // Metadata is derived from the StatsD type of each series, since names like 'pool.count' may be gauges.
+        let mut samples = Vec::with_capacity(stats.len() + 2);
+        for (stat, s) in stats {
+            if !self.announced.contains(&s.metric) {
+                self.announced.insert(s.metric.clone());
+                self.pending_metadata.push(stat.metadata(&s.metric));
+            }
+            samples.push(s);
+        }
+        samples.push(Sample::new(STATSD_PACKETS_METRIC_NAME, self.packets as f64));
+        samples.push(Sample::new(STATSD_ERRORS_METRIC_NAME, self.errors as f64));
+
+        samples
+    }
+}
+
+fn sample(name: &str, value: f64, tags: &[(String, String)]) -> Sample {
+    let tags: Tags = tags.iter().cloned().collect();
+    Sample::new_with_tags(name, value, tags)
+}
+
+/// Nearest-rank percentile of sorted, non-empty values.
+fn percentile(sorted: &[f64], p: u32) -> f64 {
+    let rank = ((p.min(100) as f64 / 100f64) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        assert_eq!(parse_line("api.requests:2|c|@0.5"),
+                   Ok(Line { name: "api.requests".to_string(), tags: vec![], value: Value::Counter(4.0) }));
+        assert_eq!(parse_line("api.connections:-3|g"),
+                   Ok(Line { name: "api.connections".to_string(), tags: vec![], value: Value::GaugeDelta(-3.0) }));
+        assert_eq!(parse_line("api.latency:12.5|ms|#route:login,env:prod"),
+                   Ok(Line {
+                       name: "api.latency".to_string(),
+                       tags: vec![("env".to_string(), "prod".to_string()), ("route".to_string(), "login".to_string())],
+                       value: Value::Timer(12.5, 1.0),
+                   }));
+        assert!(parse_line("api.latency:abc|ms").is_err());
+        assert!(parse_line("api.latency:1|x").is_err());
+        assert!(parse_line("api.latency").is_err());
+    }
+
+    #[test]
+    fn aggregate_counters_to_rates_and_timers_to_percentiles() {
+        let start = Instant::now();
+        let mut aggregator = Aggregator::new(start);
+        for line in &["api.requests:10|c", "api.requests:20|c", "api.conns:5|g", "api.conns:+2|g", "api.rate:0.5|g"] {
+            aggregator.add(parse_line(line).unwrap(), start);
+        }
+        for i in 1..101 {
+            aggregator.add(parse_line(&format!("api.latency:{}|ms|@0.5", i)).unwrap(), start);
+        }
+
+        let samples = aggregator.flush(start + Duration::from_secs(10), &[90], Duration::from_secs(300));
+        let value = |metric: &str| samples.iter().find(|s| s.metric == metric).map(|s| s.value);
+
+        assert_eq!(value("api.requests.count"), Some(30.0));
+        assert_eq!(value("api.requests.rate"), Some(3.0));
+        assert_eq!(value("api.conns"), Some(7.0));
+        assert_eq!(value("api.latency.count"), Some(200.0));
+        assert_eq!(value("api.latency.mean"), Some(50.5));
+        assert_eq!(value("api.latency.min"), Some(1.0));
+        assert_eq!(value("api.latency.max"), Some(100.0));
+        assert_eq!(value("api.latency.p90"), Some(90.0));
+        assert_eq!(aggregator.pending_metadata.len(), 9);
+        let metadata = |metric: &str| aggregator.pending_metadata.iter().find(|m| m.metric == metric).unwrap().clone();
+        assert_eq!(metadata("api.requests.rate").rate, "rate");
+        assert_eq!(metadata("api.rate").rate, "gauge");
+        assert_eq!(metadata("api.rate").description, "StatsD gauge.");
+        assert_eq!(metadata("api.latency.p90").unit, "ms");
+
+        // Gauges are kept, counters and timers are reset.
+        let samples = aggregator.flush(start + Duration::from_secs(20), &[90], Duration::from_secs(300));
+        let value = |metric: &str| samples.iter().find(|s| s.metric == metric).map(|s| s.value);
+        assert_eq!(value("api.conns"), Some(7.0));
+        assert_eq!(value("api.requests.count"), None);
+        assert_eq!(value("api.latency.count"), None);
+
+        // Gauges expire if they have not been updated.
+        let samples = aggregator.flush(start + Duration::from_secs(300), &[90], Duration::from_secs(300));
+        assert!(samples.iter().all(|s| s.metric != "api.conns"));
+    }
+}
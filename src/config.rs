@@ -1,19 +1,40 @@
 use bosun_emitter::Tags;
-use rustc_serialize::Decodable;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use serde_json;
 use toml;
 
+use crate::collectors;
+use crate::emitters::bosun::DEFAULT_BATCH_SIZE;
+use crate::scheduler::{DEFAULT_COLLECTOR_INTERVAL_SEC, DEFAULT_COLLECTOR_PROBE_INTERVAL_SEC, DEFAULT_COLLECTOR_TIMEOUT_SEC,
+                       DEFAULT_MAX_COLLECTOR_FAILURES, DEFAULT_MAX_COLLECTOR_TIMEOUTS};
 use crate::collectors::galera::GaleraConfig;
 use crate::collectors::hasipaddr::HasIpAddrConfig;
 use crate::collectors::jvm::JvmConfig;
 use crate::collectors::postfix::PostfixConfig;
 use crate::collectors::mongo::MongoConfig;
 use crate::collectors::megaraid::MegaraidConfig;
+use crate::collectors::relay::RelayConfig;
+use crate::collectors::statsd::StatsdConfig;
+use crate::emitters::graphite::GraphiteConfig;
+use crate::emitters::influx::InfluxConfig;
+use crate::emitters::jsonlines::JsonLinesConfig;
+use crate::emitters::opentsdb::OpenTsdbConfig;
+use crate::emitters::prometheus::PrometheusConfig;
+use crate::filter::{FilterConfig, MetricFilter};
+use crate::sanitizer::{self, InvalidNamePolicy};
+use crate::spool::SpoolConfig;
+use crate::utils;
 
 #[derive(Debug)]
-#[derive(RustcDecodable)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
 /// Represents connection parameters to reach Bosun as well as default tags to append to each metric
 /// datum.
@@ -22,6 +43,9 @@ pub struct Config {
     pub Host: String,
     /// Local host name
     pub Hostname: String,
+    /// Directory of drop-in files `*.conf` that are merged into this config; defaults to the config file's path with
+    /// extension `.d`, e.g., `/etc/rs-collector.d`
+    pub IncludeDir: Option<String>,
     /// Tags to always append to each metric
     pub Tags: Tags,
     /// Galera config; if enabled
@@ -29,50 +53,420 @@ pub struct Config {
     /// HasIpAddr config; if enabled
     pub HasIpAddr: Option<HasIpAddrConfig>,
     /// JVM config; if enabled
+    #[serde(default)]
     pub Jvm: Vec<JvmConfig>,
     /// Mongo configs; if enabled
+    #[serde(default)]
     pub Mongo: Vec<MongoConfig>,
     /// Postfix config; if enabled
     pub Postfix: Option<PostfixConfig>,
     /// Postfix config; if enabled
     pub Megaraid: Option<MegaraidConfig>,
+    /// Relay config; if enabled
+    pub Relay: Option<RelayConfig>,
+    /// Statsd config; if enabled
+    pub Statsd: Option<StatsdConfig>,
+    /// Max number of seconds a collection may take, unless overridden by a collector; defaults to 60
+    pub CollectorTimeout: Option<u64>,
+    /// Number of consecutive timeouts after which a collector is replaced by a new instance; defaults to 3
+    pub MaxCollectorTimeouts: Option<u32>,
+    /// Number of consecutive failed collections after which a collector is quarantined; defaults to 10
+    pub MaxCollectorFailures: Option<u32>,
+    /// Seconds between probes of a quarantined collector; defaults to 600
+    pub CollectorProbeInterval: Option<u64>,
+    /// Time between two collections, e.g., `30s`, unless overridden by a collector's `Interval`; defaults to `15s`
+    pub CollectorInterval: Option<String>,
+    /// Align collections to wall-clock multiples of their interval, so that samples of different hosts line up
+    pub AlignCollections: Option<bool>,
+    /// Max random delay of collections, e.g., `5s`, to spread load on shared databases; chosen once per collector
+    pub CollectorSplay: Option<String>,
     /// Deactivate Data Transmission to Bosun
-    pub DontSend: Option<bool>
+    pub DontSend: Option<bool>,
+    /// Max number of samples to send to Bosun in one request; defaults to 500
+    pub BatchSize: Option<usize>,
+    /// Compress requests to Bosun using gzip
+    pub Compress: Option<bool>,
+    /// Rules to drop and rename metrics of all collectors; if enabled
+    pub Filter: Option<FilterConfig>,
+    /// Handling of metric names and tags with characters OpenTSDB rejects: `sanitize` or `reject`; defaults to `sanitize`
+    pub InvalidNames: Option<InvalidNamePolicy>,
+    /// Spool config for samples that could not be sent to Bosun; if enabled
+    pub Spool: Option<SpoolConfig>,
+    /// OpenTSDB output config; if enabled
+    pub OpenTsdb: Option<OpenTsdbConfig>,
+    /// InfluxDB output config; if enabled
+    pub Influx: Option<InfluxConfig>,
+    /// Prometheus output config; if enabled
+    pub Prometheus: Option<PrometheusConfig>,
+    /// Graphite output config; if enabled
+    pub Graphite: Option<GraphiteConfig>,
+    /// JSON lines output config; if enabled
+    pub JsonLines: Option<JsonLinesConfig>,
+}
+
+/// Reasons why a configuration cannot be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file cannot be read
+    Io(io::Error),
+    /// The configuration is not valid TOML or a value has the wrong type
+    Parse(String),
+    /// A value is invalid; `section` is empty for top level values
+    Invalid { section: String, field: String, reason: String },
+    /// A section or value other than an array of tables is set in two files
+    Duplicate { key: String, first: PathBuf, second: PathBuf },
+}
+
+impl ConfigError {
+    pub fn invalid<S: Into<String>, F: Into<String>, R: Into<String>>(section: S, field: F, reason: R) -> ConfigError {
+        ConfigError::Invalid { section: section.into(), field: field.into(), reason: reason.into() }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref err) => write!(f, "failed to read config: {}", err),
+            ConfigError::Parse(ref msg) => write!(f, "failed to parse config: {}", msg),
+            ConfigError::Invalid { ref section, ref field, ref reason } if section.is_empty() =>
+                write!(f, "invalid '{}': {}", field, reason),
+            ConfigError::Invalid { ref section, ref field, ref reason } =>
+                write!(f, "invalid '{}' in section [{}]: {}", field, section, reason),
+            ConfigError::Duplicate { ref key, ref first, ref second } =>
+                write!(f, "'{}' is set in both '{}' and '{}'", key, first.display(), second.display()),
+        }
+    }
+}
+
+impl StdError for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Parse(err.to_string())
+    }
 }
 
 impl Config {
-    /// Loads a configuration from an [SCollector](http://bosun.org/scollector/) configuration file.
-    pub fn load_from_rs_collector_config(file_path: &Path) -> Result<Config, Box<dyn (::std::error::Error)>> {
-        match Config::load_toml(file_path) {
-            Ok(toml) => {
-                let mut decoder = toml::Decoder::new(toml);
-                let config = r#try!(Config::decode(&mut decoder));
-
-                Ok(config)
+    /// Loads a configuration from an [SCollector](http://bosun.org/scollector/) configuration file, merges the drop-in
+    /// files of its include directory, and validates it.
+    pub fn load_from_rs_collector_config(file_path: &Path) -> Result<Config, ConfigError> {
+        let mut config_file = File::open(file_path)?;
+        let mut config_content = String::new();
+        config_file.read_to_string(&mut config_content)?;
+
+        let mut table: toml::value::Table = toml::from_str(&config_content)?;
+        let drop_in_paths = drop_in_paths(&table, file_path)?;
+        let mut origins: HashMap<String, PathBuf> = table.keys().map(|key| (key.clone(), file_path.to_path_buf())).collect();
+        for path in &drop_in_paths {
+            let content = fs::read_to_string(path)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+            let drop_in: toml::value::Table = toml::from_str(&content)
+                .map_err(|err| ConfigError::Parse(format!("{}: {}", path.display(), err)))?;
+            merge(&mut table, drop_in, path, &mut origins)?;
+        }
+        let mut value = toml::Value::Table(table);
+
+        // Parse once before interpolation to report type errors by key and, without drop-ins, by position in the file.
+        if drop_in_paths.is_empty() {
+            let _: Config = toml::from_str(&config_content)?;
+        } else {
+            let merged = toml::to_string(&value).map_err(|err| ConfigError::Parse(err.to_string()))?;
+            let _: Config = toml::from_str(&merged)?;
+        }
+        interpolate_env(&mut value, "", "")?;
+        let mut config: Config = value.try_into()?;
+        config.resolve_secrets()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Reads passwords from the `PasswordFile`s of all sections.
+    pub fn resolve_secrets(&mut self) -> Result<(), ConfigError> {
+        if let Some(ref mut galera) = self.Galera {
+            galera.resolve_secrets()?;
+        }
+        for mongo in &mut self.Mongo {
+            mongo.resolve_secrets()?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks values that cannot be checked by their types, e.g., durations and settings that require each other.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        collectors::parse_interval(&self.CollectorInterval)
+            .map_err(|err| ConfigError::invalid("", "CollectorInterval", err))?;
+        if let Some(ref splay) = self.CollectorSplay {
+            utils::parse_duration(splay).map_err(|err| ConfigError::invalid("", "CollectorSplay", err))?;
+        }
+        // The global tags are added by the outputs after invalid names have been handled.
+        sanitizer::validate_tags(&self.Tags).map_err(|err| ConfigError::invalid("", "Tags", err))?;
+        if let Some(ref filter) = self.Filter {
+            MetricFilter::new(filter, "Filter")?;
+        }
+
+        if let Some(ref galera) = self.Galera {
+            galera.validate()?;
+        }
+        if let Some(ref hasipaddr) = self.HasIpAddr {
+            hasipaddr.validate()?;
+        }
+        if let Some(ref megaraid) = self.Megaraid {
+            megaraid.validate()?;
+        }
+        let mut jvm_names = HashSet::new();
+        for jvm in &self.Jvm {
+            jvm.validate(self.InvalidNames.unwrap_or_default())?;
+            // Each `[[Jvm]]` section is a collector of its own, identified by its name.
+            if !jvm_names.insert(&jvm.Name) {
+                return Err(ConfigError::invalid("Jvm", "Name", format!("'{}' is used by more than one section", jvm.Name)));
             }
-            Err(err) => Err(err),
         }
+        for mongo in &self.Mongo {
+            mongo.validate()?;
+        }
+        if let Some(ref postfix) = self.Postfix {
+            postfix.validate()?;
+        }
+        if let Some(ref relay) = self.Relay {
+            relay.validate()?;
+        }
+        if let Some(ref statsd) = self.Statsd {
+            statsd.validate()?;
+        }
+
+        if let Some(ref spool) = self.Spool {
+            spool.validate()?;
+        }
+        if let Some(ref influx) = self.Influx {
+            influx.validate()?;
+        }
+        if let Some(ref prometheus) = self.Prometheus {
+            prometheus.validate()?;
+        }
+        if let Some(ref graphite) = self.Graphite {
+            graphite.validate()?;
+        }
+        if let Some(ref jsonlines) = self.JsonLines {
+            jsonlines.validate()?;
+        }
+
+        Ok(())
     }
 
-    fn load_toml(file_path: &Path) -> Result<toml::Value, Box<dyn (::std::error::Error)>> {
-        let mut config_file = r#try!(File::open(file_path));
-        let mut config_content = String::new();
-        r#try!(config_file.read_to_string(&mut config_content));
+    /// Returns a copy of this configuration with the defaults of all unset values of the top level and enabled sections.
+    pub fn effective(&self) -> Config {
+        let mut config = self.clone();
+        config.CollectorTimeout.get_or_insert(DEFAULT_COLLECTOR_TIMEOUT_SEC);
+        config.MaxCollectorTimeouts.get_or_insert(DEFAULT_MAX_COLLECTOR_TIMEOUTS);
+        config.MaxCollectorFailures.get_or_insert(DEFAULT_MAX_COLLECTOR_FAILURES);
+        config.CollectorProbeInterval.get_or_insert(DEFAULT_COLLECTOR_PROBE_INTERVAL_SEC);
+        config.CollectorInterval.get_or_insert_with(|| utils::format_duration(Duration::from_secs(DEFAULT_COLLECTOR_INTERVAL_SEC)));
+        config.AlignCollections.get_or_insert(false);
+        config.CollectorSplay.get_or_insert_with(|| "0s".to_string());
+        config.DontSend.get_or_insert(false);
+        config.BatchSize.get_or_insert(DEFAULT_BATCH_SIZE);
+        config.Compress.get_or_insert(false);
+        config.InvalidNames.get_or_insert_with(InvalidNamePolicy::default);
+
+        if let Some(ref mut galera) = config.Galera {
+            galera.apply_defaults();
+        }
+        if let Some(ref mut megaraid) = config.Megaraid {
+            megaraid.apply_defaults();
+        }
+        for mongo in &mut config.Mongo {
+            mongo.apply_defaults();
+        }
+        if let Some(ref mut relay) = config.Relay {
+            relay.apply_defaults();
+        }
+        if let Some(ref mut statsd) = config.Statsd {
+            statsd.apply_defaults();
+        }
+        if let Some(ref mut spool) = config.Spool {
+            spool.apply_defaults();
+        }
+        if let Some(ref mut opentsdb) = config.OpenTsdb {
+            opentsdb.apply_defaults();
+        }
+        if let Some(ref mut influx) = config.Influx {
+            influx.apply_defaults();
+        }
+        if let Some(ref mut prometheus) = config.Prometheus {
+            prometheus.apply_defaults();
+        }
+        if let Some(ref mut graphite) = config.Graphite {
+            graphite.apply_defaults();
+        }
+        if let Some(ref mut jsonlines) = config.JsonLines {
+            jsonlines.apply_defaults();
+        }
+
+        config
+    }
+
+    /// Returns a copy of this configuration with passwords replaced by `<redacted>`, so that it can be shown.
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+        config.Host = utils::redact_url(&config.Host);
+        if let Some(ref mut galera) = config.Galera {
+            galera.Password = galera.Password.as_ref().map(|_| utils::REDACTED.to_string());
+        }
+        for mongo in &mut config.Mongo {
+            mongo.Password = mongo.Password.as_ref().map(|_| utils::REDACTED.to_string());
+        }
+        if let Some(ref mut influx) = config.Influx {
+            influx.Url = utils::redact_url(&influx.Url);
+        }
+
+        config
+    }
+
+    /// Renders the effective, redacted configuration together with the collectors it instantiates.
+    pub fn show(&self, format: Format) -> Result<String, String> {
+        let collectors = collectors::create_collectors(self).map_err(|e| e.to_string())?.iter()
+            .map(|c| ShowCollector {
+                Id: c.id().clone(),
+                Interval: c.get_interval().map(utils::format_duration),
+                Timeout: c.get_timeout(),
+            })
+            .collect();
+        let config = self.effective().redacted();
+        let show = ShowConfig { Config: &config, Collectors: collectors };
+
+        match format {
+            // Convert to a TOML value first, because it orders plain values before tables as TOML requires.
+            Format::Toml => toml::Value::try_from(&show)
+                .and_then(|value| toml::to_string(&value))
+                .map_err(|e| e.to_string()),
+            Format::Json => serde_json::to_string_pretty(&show).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Returns the drop-in files `*.conf` of the include directory in lexical order.
+fn drop_in_paths(config: &toml::value::Table, file_path: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+    let include_dir = match config.get("IncludeDir") {
+        Some(&toml::Value::String(ref dir)) => file_path.parent().unwrap_or_else(|| Path::new("")).join(dir),
+        Some(_) => return Err(ConfigError::invalid("", "IncludeDir", "must be a string")),
+        // The default directory is optional.
+        None if !file_path.with_extension("d").is_dir() => return Ok(Vec::new()),
+        None => file_path.with_extension("d"),
+    };
+
+    let entries = fs::read_dir(&include_dir)
+        .map_err(|err| ConfigError::invalid("", "IncludeDir", format!("failed to read '{}': {}", include_dir.display(), err)))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() && path.extension().map_or(false, |ext| ext == "conf") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Merges a drop-in file into the config: arrays of tables like `[[Mongo]]` are concatenated; any other key may only be
+/// set once.
+fn merge(config: &mut toml::value::Table, drop_in: toml::value::Table, path: &Path, origins: &mut HashMap<String, PathBuf>) -> Result<(), ConfigError> {
+    for (key, value) in drop_in {
+        match (config.get_mut(&key), value) {
+            (Some(toml::Value::Array(values)), toml::Value::Array(more_values)) => values.extend(more_values),
+            (Some(_), _) => {
+                return Err(ConfigError::Duplicate { key: key.clone(), first: origins[&key].clone(), second: path.to_path_buf() });
+            }
+            (None, value) => {
+                origins.insert(key.clone(), path.to_path_buf());
+                config.insert(key, value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces `${NAME}` in all string values by the value of the environment variable `NAME`.
+fn interpolate_env(value: &mut toml::Value, section: &str, field: &str) -> Result<(), ConfigError> {
+    match *value {
+        toml::Value::String(ref mut s) => {
+            *s = utils::interpolate_env(s).map_err(|err| ConfigError::invalid(section, field, err))?;
+        }
+        toml::Value::Array(ref mut values) => {
+            for value in values {
+                interpolate_env(value, section, field)?;
+            }
+        }
+        toml::Value::Table(ref mut table) => {
+            // Values of the top level table have no section; values of nested tables are named by their table.
+            let section = match (section.is_empty(), field.is_empty()) {
+                (_, true) => section.to_string(),
+                (true, false) => field.to_string(),
+                (false, false) => format!("{}.{}", section, field),
+            };
+            for (key, value) in table.iter_mut() {
+                interpolate_env(value, &section, key)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Output formats of `Config::show`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
 
-        let mut parser = toml::Parser::new(&config_content);
-        match parser.parse() {
-            Some(toml) => Ok(toml::Value::Table(toml)),
-            None => Err(From::from(parser.errors.pop().unwrap())),
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "toml" => Ok(Format::Toml),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format '{}'", s)),
         }
     }
 }
 
+#[derive(Serialize)]
+#[allow(non_snake_case)]
+struct ShowConfig<'a> {
+    #[serde(flatten)]
+    Config: &'a Config,
+    Collectors: Vec<ShowCollector>,
+}
+
+#[derive(Serialize)]
+#[allow(non_snake_case)]
+struct ShowCollector {
+    Id: String,
+    /// `None` if the collector uses the global `CollectorInterval`
+    Interval: Option<String>,
+    /// `None` if the collector uses the global `CollectorTimeout`
+    Timeout: Option<u64>,
+}
+
 impl Default for Config {
     /// Creates a default configuration for `localhost`, port `8070`.
     fn default() -> Config {
         Config {
             Host: "localhost:8070".to_string(),
             Hostname: "localhost".to_string(),
+            IncludeDir: None,
             Tags: Tags::new(),
             Galera: None,
             HasIpAddr: None,
@@ -80,7 +474,26 @@ impl Default for Config {
             Mongo: Vec::new(),
             Postfix: None,
             Megaraid: None,
+            Relay: None,
+            Statsd: None,
+            CollectorTimeout: None,
+            MaxCollectorTimeouts: None,
+            MaxCollectorFailures: None,
+            CollectorProbeInterval: None,
+            CollectorInterval: None,
+            AlignCollections: None,
+            CollectorSplay: None,
             DontSend: Some(false),
+            BatchSize: None,
+            Compress: None,
+            Filter: None,
+            InvalidNames: None,
+            Spool: None,
+            OpenTsdb: None,
+            Influx: None,
+            Prometheus: None,
+            Graphite: None,
+            JsonLines: None,
         }
     }
 }
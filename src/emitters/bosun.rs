@@ -0,0 +1,428 @@
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::collectors::rscollector::{RS_COLLECTOR_STATS_SAMPLES_METRICNAME, RS_COLLECTOR_SPOOL_SPOOLED_METRICNAME,
+    RS_COLLECTOR_SPOOL_REPLAYED_METRICNAME, RS_COLLECTOR_SPOOL_DROPPED_METRICNAME, RS_COLLECTOR_SPOOL_SIZE_METRICNAME,
+    RS_COLLECTOR_BOSUN_SEND_ERRORS_METRICNAME, RS_COLLECTOR_BOSUN_CIRCUIT_STATE_METRICNAME};
+use crate::config::{Config, ConfigError};
+use crate::emitters::*;
+use crate::spool::{Replayed, Spool};
+
+use bosun_emitter::{Datum, EmitterError};
+use bosun_emitter;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use reqwest::{self, StatusCode, Url};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+static MAX_REPLAY_SAMPLES_PER_TICK: usize = 1000;
+pub static DEFAULT_BATCH_SIZE: usize = 500;
+static MAX_BATCH_RETRIES: usize = 1;
+static SEND_TIMEOUT_SEC: u64 = 5u64;
+
+pub struct Bosun {
+    id: Id,
+    queue: Vec<Sample>,
+    /// Metadata waiting to be sent, by metric name, so that metadata sent again while Bosun is down is queued once
+    metadata: BTreeMap<String, Metadata>,
+    sender: BatchSender,
+    spool: Option<Spool>,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Emitter + Send>>, ConfigError> {
+    if config.DontSend.unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+
+    let spool = match config.Spool {
+        Some(ref spool_config) => Some(Spool::new(spool_config).map_err(|e| ConfigError::invalid("Spool", "Directory",
+            format!("failed to open spool directory '{}': {}", spool_config.Directory, e)))?),
+        None => None,
+    };
+    let bosun = Bosun::new(config, spool);
+    info!("Created instance of Bosun emitter: {}", bosun.id);
+
+    Ok(vec![Box::new(bosun)])
+}
+
+impl Bosun {
+    pub fn new(config: &Config, spool: Option<Spool>) -> Bosun {
+        Bosun {
+            id: format!("bosun@{}", config.Host),
+            queue: Vec::new(),
+            metadata: BTreeMap::new(),
+            sender: create_sender(config),
+            spool: spool,
+        }
+    }
+
+    /// Spools samples that could not be sent. If all samples have been sent and the circuit is healthy, Bosun is
+    /// reachable again and previously spooled samples are replayed.
+    fn spool_or_replay(&mut self, unsent: Vec<Sample>) {
+        let spool = match self.spool {
+            Some(ref mut spool) => spool,
+            None => {
+                if !unsent.is_empty() {
+                    debug!("Dropping {} unsent samples, because spool is not enabled.", unsent.len());
+                }
+                return
+            }
+        };
+
+        if !unsent.is_empty() {
+            debug!("Spooling {} unsent samples.", unsent.len());
+            spool.push(unsent);
+        } else if !spool.is_empty() && self.sender.circuit_breaker.state() == CircuitState::Healthy {
+            let sender = &mut self.sender;
+            let batch_size = sender.batch_size;
+            spool.replay(MAX_REPLAY_SAMPLES_PER_TICK, batch_size, |batch| {
+                match sender.send_batch(batch) {
+                    Ok(_) => Ok(Replayed::Sent),
+                    // Bosun will reject this batch again, so it is removed from the spool.
+                    Err(SendError::Rejected(msg)) => {
+                        error!("Bosun rejected spooled batch of {} samples, because {}. Dropping batch.", batch.len(), msg);
+                        Ok(Replayed::Dropped)
+                    }
+                    Err(err) => Err(err),
+                }
+            });
+        }
+    }
+
+    /// Sends queued metadata unless the circuit is open; metadata Bosun could not be reached for stays queued.
+    /// Failures are counted by the sender and logged by the circuit breaker rather than for each metadata.
+    fn send_metadata(&mut self) {
+        let metadata = mem::take(&mut self.metadata);
+        for (metric, m) in metadata {
+            if !self.sender.circuit_breaker.allows_request(Instant::now()) {
+                self.metadata.insert(metric, m);
+                continue;
+            }
+            match self.sender.send_metadata(&m) {
+                Ok(_) => {},
+                Err(SendError::Rejected(msg)) => {
+                    debug!("Bosun rejected metadata of '{}', because {}. Dropping metadata.", metric, msg);
+                }
+                Err(SendError::Unavailable(_)) => {
+                    self.metadata.insert(metric, m);
+                }
+            }
+        }
+    }
+
+    fn push_sender_stats(&mut self) {
+        let send_errors = self.sender.send_errors as f64;
+        let circuit_state = self.sender.circuit_breaker.state().value();
+        self.queue.push(Sample::new(RS_COLLECTOR_BOSUN_SEND_ERRORS_METRICNAME, send_errors));
+        self.queue.push(Sample::new(RS_COLLECTOR_BOSUN_CIRCUIT_STATE_METRICNAME, circuit_state));
+    }
+
+    fn push_spool_stats(&mut self) {
+        if let Some(ref spool) = self.spool {
+            let stats = spool.stats().clone();
+            self.queue.push(Sample::new(RS_COLLECTOR_SPOOL_SPOOLED_METRICNAME, stats.spooled as f64));
+            self.queue.push(Sample::new(RS_COLLECTOR_SPOOL_REPLAYED_METRICNAME, stats.replayed as f64));
+            self.queue.push(Sample::new(RS_COLLECTOR_SPOOL_DROPPED_METRICNAME, stats.dropped as f64));
+            self.queue.push(Sample::new(RS_COLLECTOR_SPOOL_SIZE_METRICNAME, spool.size() as f64));
+        }
+    }
+}
+
+impl Emitter for Bosun {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn emit_metadata(&mut self, metadata: &Metadata) {
+        self.metadata.insert(metadata.metric.clone(), metadata.clone());
+    }
+
+    fn emit_sample(&mut self, sample: Sample) {
+        self.queue.push(sample);
+    }
+
+    fn flush(&mut self) {
+        self.send_metadata();
+        let queue_len = self.queue.len() as f64 + 1f64;
+        self.queue.push(Sample::new(RS_COLLECTOR_STATS_SAMPLES_METRICNAME, queue_len));
+        self.push_spool_stats();
+        self.push_sender_stats();
+        debug!("Current sample queue length is {:#?}. Sending data now.", queue_len);
+        let samples: Vec<Sample> = self.queue.drain(..).collect();
+        let unsent = self.sender.send(samples);
+        self.spool_or_replay(unsent);
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(ref mut spool) = self.spool {
+            spool.flush(self.queue.drain(..).collect());
+        }
+    }
+
+    /// Switches to the new Bosun host and tags; queued and spooled samples are sent to the new host.
+    fn reload(&mut self, config: &Config) {
+        let send_errors = self.sender.send_errors;
+        self.id = format!("bosun@{}", config.Host);
+        self.sender = create_sender(config);
+        self.sender.send_errors = send_errors;
+    }
+}
+
+fn create_sender(config: &Config) -> BatchSender {
+    BatchSender::new(&config.Host,
+                     &config.Hostname,
+                     &config.Tags,
+                     config.BatchSize.unwrap_or(DEFAULT_BATCH_SIZE),
+                     config.Compress.unwrap_or(false))
+}
+
+#[derive(Debug)]
+enum SendError {
+    /// Bosun could not be reached or failed to process the request; sending may succeed later.
+    Unavailable(String),
+    /// Bosun rejected the request, e.g., because of invalid data points; sending it again will fail again.
+    Rejected(String),
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::Unavailable(ref msg) => write!(f, "Bosun is unavailable: {}", msg),
+            SendError::Rejected(ref msg) => write!(f, "Bosun rejected request: {}", msg),
+        }
+    }
+}
+
+impl From<EmitterError> for SendError {
+    fn from(err: EmitterError) -> Self {
+        match err {
+            // Encoding the same samples will fail again.
+            EmitterError::JsonParseError(_) => SendError::Rejected(format!("{:?}", err)),
+            _ => SendError::Unavailable(format!("{:?}", err)),
+        }
+    }
+}
+
+/// Sends samples as JSON arrays to Bosun's `/api/put` endpoint, so that many samples require only one request.
+/// Requests are guarded by a circuit breaker, so that an unreachable Bosun is not hammered with requests.
+struct BatchSender {
+    http_client: Option<reqwest::Client>,
+    host: String,
+    hostname: String,
+    default_tags: Tags,
+    batch_size: usize,
+    compress: bool,
+    circuit_breaker: CircuitBreaker,
+    send_errors: u64,
+}
+
+impl BatchSender {
+    fn new(host: &str, hostname: &str, default_tags: &Tags, batch_size: usize, compress: bool) -> BatchSender {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(SEND_TIMEOUT_SEC))
+            .build();
+        let http_client = match http_client {
+            Ok(client) => Some(client),
+            Err(err) => {
+                error!("Failed to build http client for Bosun, because {}.", err);
+                None
+            }
+        };
+
+        BatchSender {
+            http_client,
+            host: host.to_string(),
+            hostname: hostname.to_string(),
+            default_tags: default_tags.clone(),
+            batch_size: batch_size.max(1),
+            compress,
+            circuit_breaker: CircuitBreaker::new(format!("Bosun at {}", host)),
+            send_errors: 0,
+        }
+    }
+
+    /// Sends samples in batches and returns the samples of all batches that could not be sent, either because Bosun is
+    /// unavailable or because the circuit is open. Batches rejected by Bosun are dropped.
+    fn send(&mut self, samples: Vec<Sample>) -> Vec<Sample> {
+        let mut unsent = Vec::new();
+        let mut batches = samples.into_iter().peekable();
+        while batches.peek().is_some() {
+            let batch: Vec<Sample> = batches.by_ref().take(self.batch_size).collect();
+            if !self.circuit_breaker.allows_request(Instant::now()) {
+                unsent.extend(batch);
+                continue;
+            }
+            match self.send_batch_with_retries(&batch) {
+                Ok(_) => {},
+                Err(SendError::Rejected(msg)) => {
+                    error!("Bosun rejected batch of {} samples, because {}. Dropping batch.", batch.len(), msg);
+                }
+                Err(SendError::Unavailable(_)) => {
+                    unsent.extend(batch);
+                }
+            }
+        }
+
+        unsent
+    }
+
+    fn send_batch_with_retries(&mut self, batch: &[Sample]) -> Result<(), SendError> {
+        let mut result = self.send_batch(batch);
+        let mut retries = 0;
+        while let Err(SendError::Unavailable(_)) = result {
+            if retries >= MAX_BATCH_RETRIES || !self.circuit_breaker.allows_request(Instant::now()) {
+                break;
+            }
+            retries += 1;
+            debug!("Retrying to send batch of {} samples to Bosun ({}/{}).", batch.len(), retries, MAX_BATCH_RETRIES);
+            result = self.send_batch(batch);
+        }
+
+        result
+    }
+
+    fn send_batch(&mut self, batch: &[Sample]) -> Result<(), SendError> {
+        let json = samples_to_json(batch, &self.hostname, &self.default_tags)?;
+        trace!("Sending batch {} to Bosun.", &json);
+        let result = self.post("/api/put", json.into_bytes(), self.compress);
+        self.on_result(&result);
+
+        result
+    }
+
+    fn send_metadata(&mut self, metadata: &Metadata) -> Result<(), SendError> {
+        let m = bosun_emitter::Metadata {
+            metric: &metadata.metric, rate: &metadata.rate, unit: &metadata.unit, description: &metadata.description };
+        let json = m.to_json()?;
+        trace!("Sending metadata {} to Bosun.", &json);
+        let result = self.post("/api/metadata/put", json.into_bytes(), false);
+        self.on_result(&result);
+
+        result
+    }
+
+    fn on_result(&mut self, result: &Result<(), SendError>) {
+        match *result {
+            Ok(_) => self.circuit_breaker.on_success(),
+            Err(SendError::Rejected(_)) => {
+                // Bosun is reachable, but did not like the data.
+                self.send_errors += 1;
+                self.circuit_breaker.on_success();
+            }
+            Err(SendError::Unavailable(ref msg)) => {
+                self.send_errors += 1;
+                self.circuit_breaker.on_failure(Instant::now(), msg);
+            }
+        }
+    }
+
+    fn post(&self, path: &str, body: Vec<u8>, compress: bool) -> Result<(), SendError> {
+        let http_client = match self.http_client {
+            Some(ref client) => client,
+            None => return Err(SendError::Unavailable("no http client available".to_string())),
+        };
+        let uri = if self.host.starts_with("http") {
+            format!("{}{}", self.host, path)
+        } else {
+            format!("http://{}{}", self.host, path)
+        };
+        let url = Url::parse(&uri)
+            .map_err(|e| SendError::Unavailable(format!("invalid Bosun url '{}' because {}", uri, e)))?;
+
+        let req = http_client
+            .post(url.clone())
+            .header("Content-Type", "application/json; charset=utf-8");
+        let req = if compress {
+            req.header("Content-Encoding", "gzip").body(gzip(&body)?)
+        } else {
+            req.body(body)
+        };
+        let req = match (url.username(), url.password()) {
+            (username, Some(password)) if !username.is_empty() => req.basic_auth(username, Some(password)),
+            _ => req,
+        };
+
+        match req.send() {
+            Ok(ref response) if response.status() == StatusCode::NO_CONTENT => Ok(()),
+            Ok(ref response) if response.status().is_client_error() =>
+                Err(SendError::Rejected(format!("{}", response.status()))),
+            Ok(response) => Err(SendError::Unavailable(format!("{}", response.status()))),
+            Err(err) => Err(SendError::Unavailable(format!("{}", err))),
+        }
+    }
+}
+
+fn samples_to_json(samples: &[Sample], hostname: &str, default_tags: &Tags) -> Result<String, EmitterError> {
+    let mut data = Vec::with_capacity(samples.len());
+    for s in samples {
+        let value = format!("{}", &s.value);
+        let tags = tags_with_defaults(s, hostname, default_tags);
+        let d = Datum {
+            metric: &s.metric, timestamp: s.time as i64, value: &value, tags: &tags };
+        data.push(d.to_json()?);
+    }
+
+    Ok(format!("[{}]", data.join(",")))
+}
+
+fn gzip(body: &[u8]) -> Result<Vec<u8>, EmitterError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    let compressed = encoder.finish()?;
+
+    Ok(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_to_json_array() {
+        let mut tags = Tags::new();
+        tags.insert("jvm_name".to_string(), "tomcat".to_string());
+        let samples = vec![
+            Sample { time: 10, metric: "jvm.gc.stats.full_gc_events".to_string(), value: 1.0, tags: tags },
+            Sample { time: 20, metric: "rs-collector.version".to_string(), value: 2.5, tags: Tags::new() },
+        ];
+        let mut default_tags = Tags::new();
+        default_tags.insert("domain".to_string(), "example.com".to_string());
+
+        let json = samples_to_json(&samples, "webserver", &default_tags).unwrap();
+
+        let parsed: ::serde_json::Value = ::serde_json::from_str(&json).unwrap();
+        let data = parsed.as_array().unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0]["metric"].as_str(), Some("jvm.gc.stats.full_gc_events"));
+        assert_eq!(data[0]["timestamp"].as_i64(), Some(10));
+        assert_eq!(data[0]["tags"]["jvm_name"].as_str(), Some("tomcat"));
+        assert_eq!(data[0]["tags"]["host"].as_str(), Some("webserver"));
+        assert_eq!(data[1]["value"].as_str(), Some("2.5"));
+        assert_eq!(data[1]["tags"]["domain"].as_str(), Some("example.com"));
+    }
+
+    #[test]
+    fn queue_metadata_while_bosun_is_unreachable() {
+        let mut config = Config::default();
+        config.Host = "127.0.0.1:1".to_string();
+        let mut bosun = Bosun::new(&config, None);
+        let metadata = Metadata::new("app.requests", crate::bosun::Rate::Counter, "Requests", "Number of requests");
+        bosun.emit_metadata(&metadata);
+        bosun.emit_metadata(&metadata);
+        bosun.emit_metadata(&Metadata::new("app.errors", crate::bosun::Rate::Counter, "Errors", "Number of errors"));
+
+        bosun.send_metadata();
+        assert_eq!(bosun.metadata.len(), 2);
+        assert_eq!(bosun.sender.send_errors, 2);
+
+        for _ in 0..10 {
+            bosun.sender.circuit_breaker.on_failure(Instant::now(), "connection refused");
+        }
+        bosun.send_metadata();
+        assert_eq!(bosun.metadata.len(), 2);
+        assert_eq!(bosun.sender.send_errors, 2);
+    }
+}
@@ -0,0 +1,279 @@
+// See https://graphite.readthedocs.io/en/latest/feeding-carbon.html
+
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::config::{Config, ConfigError};
+use crate::emitters::*;
+use crate::emitters::tcp::TcpLineSender;
+
+use regex::Regex;
+
+static DEFAULT_PORT: u16 = 2003;
+static DEFAULT_MAX_QUEUE_SIZE: usize = 10000;
+static DEFAULT_TEMPLATE: &'static str = "{host}.{metric}";
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct GraphiteConfig {
+    /// Graphite host name and port of the plaintext receiver; the port defaults to 2003
+    pub Host: String,
+    /// Prefix prepended to every metric path
+    pub Prefix: Option<String>,
+    /// Append tags using Graphite's `;tag=value` syntax instead of folding them into the path by templates
+    pub TagSyntax: Option<bool>,
+    /// Templates to fold tags into metric paths; the first matching template is used
+    #[serde(default)]
+    pub Templates: Vec<GraphiteTemplateConfig>,
+    /// Max number of samples to keep while Graphite is unreachable; defaults to 10000
+    pub MaxQueueSize: Option<usize>,
+}
+
+impl GraphiteConfig {
+    pub fn apply_defaults(&mut self) {
+        self.TagSyntax.get_or_insert(false);
+        self.MaxQueueSize.get_or_insert(DEFAULT_MAX_QUEUE_SIZE);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        create_templates(&self.Templates).map(|_| ())
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct GraphiteTemplateConfig {
+    /// Regex matched against metric names; omit to match all metrics
+    pub Filter: Option<String>,
+    /// Metric path with placeholders `{metric}` for the metric name and `{<tag>}` for tag values, e.g.,
+    /// `{host}.jvm.{jvm_name}.{metric}`
+    pub Path: String,
+}
+
+#[derive(Debug)]
+struct Template {
+    filter: Option<Regex>,
+    path: Vec<PathPart>,
+}
+
+#[derive(Debug, PartialEq)]
+enum PathPart {
+    Literal(String),
+    Metric,
+    Tag(String),
+}
+
+/// Sends samples using Graphite's plaintext protocol `<path> <value> <timestamp>` over a persistent TCP connection.
+pub struct Graphite {
+    id: Id,
+    hostname: String,
+    default_tags: Tags,
+    prefix: Option<String>,
+    tag_syntax: bool,
+    templates: Vec<Template>,
+    sender: TcpLineSender,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Emitter + Send>>, ConfigError> {
+    match config.Graphite {
+        Some(ref graphite_config) => {
+            let host = if graphite_config.Host.contains(':') {
+                graphite_config.Host.clone()
+            } else {
+                format!("{}:{}", graphite_config.Host, DEFAULT_PORT)
+            };
+            let id = format!("graphite@{}", host);
+
+            let templates = create_templates(&graphite_config.Templates)?;
+            let max_queue_size = graphite_config.MaxQueueSize.unwrap_or(DEFAULT_MAX_QUEUE_SIZE);
+            let emitter = Graphite {
+                id: id.clone(),
+                hostname: config.Hostname.clone(),
+                default_tags: config.Tags.clone(),
+                prefix: graphite_config.Prefix.clone(),
+                tag_syntax: graphite_config.TagSyntax.unwrap_or(false),
+                templates,
+                sender: TcpLineSender::new("Graphite", &host, max_queue_size),
+            };
+            info!("Created instance of Graphite emitter: {}", id);
+
+            Ok(vec![Box::new(emitter)])
+        }
+        None => {
+            Ok(Vec::new())
+        }
+    }
+}
+
+fn create_templates(configs: &[GraphiteTemplateConfig]) -> Result<Vec<Template>, ConfigError> {
+    let invalid = |field: &str, reason: String| ConfigError::invalid("Graphite.Templates", field, reason);
+    let mut templates = Vec::new();
+    for config in configs {
+        let filter = match config.Filter {
+            Some(ref filter) =>
+                Some(Regex::new(filter).map_err(|e| invalid("Filter", format!("invalid regex '{}': {}", filter, e)))?),
+            None => None,
+        };
+        templates.push(Template { filter, path: parse_path(&config.Path).map_err(|e| invalid("Path", e))? });
+    }
+    templates.push(Template { filter: None, path: parse_path(DEFAULT_TEMPLATE).map_err(|e| invalid("Path", e))? });
+
+    Ok(templates)
+}
+
+fn parse_path(path: &str) -> Result<Vec<PathPart>, String> {
+    let mut parts = Vec::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            parts.push(PathPart::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..].find('}')
+            .ok_or_else(|| format!("unclosed placeholder in template path '{}'", path))? + start;
+        match &rest[start + 1..end] {
+            name if name.is_empty() || name.contains('{') =>
+                return Err(format!("invalid placeholder '{{{}}}' in template path '{}'", name, path)),
+            "metric" => parts.push(PathPart::Metric),
+            tag => parts.push(PathPart::Tag(tag.to_string())),
+        }
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(PathPart::Literal(rest.to_string()));
+    }
+
+    Ok(parts)
+}
+
+impl Emitter for Graphite {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn emit_metadata(&mut self, _: &Metadata) {
+        // Graphite's plaintext protocol has no notion of metadata.
+    }
+
+    fn emit_sample(&mut self, sample: Sample) {
+        if !sample.value.is_finite() {
+            debug!("Skipping sample '{}' with non-finite value {}.", sample.metric, sample.value);
+            return;
+        }
+        let tags = tags_with_defaults(&sample, &self.hostname, &self.default_tags);
+        let path = if self.tag_syntax {
+            tagged_path(&sample.metric, &tags)
+        } else {
+            match self.templates.iter().filter_map(|t| t.apply(&sample.metric, &tags)).next() {
+                Some(path) => path,
+                None => {
+                    debug!("Skipping sample '{}', because no template matches.", sample.metric);
+                    return;
+                }
+            }
+        };
+        let path = match self.prefix {
+            Some(ref prefix) => format!("{}.{}", prefix, path),
+            None => path,
+        };
+
+        self.sender.push(format!("{} {} {}\n", path, sample.value, sample.time));
+    }
+
+    fn flush(&mut self) {
+        self.sender.flush();
+    }
+
+    fn shutdown(&mut self) {
+        self.sender.shutdown();
+    }
+
+    fn reload(&mut self, config: &Config) {
+        self.hostname = config.Hostname.clone();
+        self.default_tags = config.Tags.clone();
+    }
+}
+
+impl Template {
+    /// Returns the metric path for a metric, if the filter matches and all tags referenced by the path are present.
+    fn apply(&self, metric: &str, tags: &Tags) -> Option<String> {
+        if let Some(ref filter) = self.filter {
+            if !filter.is_match(metric) {
+                return None;
+            }
+        }
+
+        let mut path = String::new();
+        for part in &self.path {
+            match *part {
+                PathPart::Literal(ref literal) => path.push_str(literal),
+                PathPart::Metric => path.push_str(metric),
+                PathPart::Tag(ref tag) => path.push_str(&sanitize_path_node(tags.get(tag)?)),
+            }
+        }
+
+        Some(path)
+    }
+}
+
+/// Tag values become single nodes of a metric path, so they must not contain dots, e.g., IP addresses.
+fn sanitize_path_node(value: &str) -> String {
+    value.chars().map(|c| if c == '.' || c == '/' || c.is_whitespace() { '_' } else { c }).collect()
+}
+
+/// Formats a metric path using Graphite's tag syntax `<metric>;<tag>=<value>;...`. Tags are sorted to get stable paths.
+fn tagged_path(metric: &str, tags: &Tags) -> String {
+    let mut tags: Vec<String> = tags.iter()
+        .filter(|&(_, v)| !v.is_empty())
+        .map(|(k, v)| format!("{}={}", sanitize_tag(k), sanitize_tag(v)))
+        .collect();
+    tags.sort();
+
+    if tags.is_empty() { metric.to_string() } else { format!("{};{}", metric, tags.join(";")) }
+}
+
+fn sanitize_tag(value: &str) -> String {
+    value.chars().map(|c| if c == ';' || c == '~' || c == '=' || c.is_whitespace() { '_' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&str, &str)]) -> Tags {
+        pairs.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn first_matching_template_wins() {
+        let configs = vec![
+            GraphiteTemplateConfig { Filter: Some("^jvm\\.".to_string()), Path: "{host}.jvm.{jvm_name}.{metric}".to_string() },
+            GraphiteTemplateConfig { Filter: None, Path: "{host}.{ipv4}.{metric}".to_string() },
+        ];
+        let templates = create_templates(&configs).unwrap();
+        let apply = |metric: &str, tags: &Tags| templates.iter().filter_map(|t| t.apply(metric, tags)).next();
+
+        assert_eq!(apply("jvm.gc.stats.full_gc_events", &tags(&[("host", "web"), ("jvm_name", "tomcat")])),
+                   Some("web.jvm.tomcat.jvm.gc.stats.full_gc_events".to_string()));
+        assert_eq!(apply("hasipaddr.has_ip", &tags(&[("host", "web"), ("ipv4", "10.0.0.1")])),
+                   Some("web.10_0_0_1.hasipaddr.has_ip".to_string()));
+        // Falls back to the default template, because tag 'ipv4' is missing.
+        assert_eq!(apply("mongo.uptime", &tags(&[("host", "web"), ("replicaset", "rs01")])),
+                   Some("web.mongo.uptime".to_string()));
+    }
+
+    #[test]
+    fn parse_template_path() {
+        assert_eq!(parse_path("{host}.jvm.{metric}"),
+                   Ok(vec![PathPart::Tag("host".to_string()), PathPart::Literal(".jvm.".to_string()), PathPart::Metric]));
+        assert!(parse_path("{host.{metric}").is_err());
+    }
+
+    #[test]
+    fn tagged_path_uses_graphite_tag_syntax() {
+        let path = tagged_path("mongo.uptime", &tags(&[("host", "web"), ("replicaset", "rs 01")]));
+
+        assert_eq!(path, "mongo.uptime;host=web;replicaset=rs_01");
+    }
+}
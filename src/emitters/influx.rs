@@ -0,0 +1,343 @@
+// See https://docs.influxdata.com/influxdb/v1.8/write_protocols/line_protocol_reference/
+
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::{Config, ConfigError};
+use crate::emitters::*;
+use crate::utils;
+
+use reqwest::{self, StatusCode, Url};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+static DEFAULT_MAX_QUEUE_SIZE: usize = 10000;
+static DEFAULT_FIELD_NAME: &'static str = "value";
+static SEND_TIMEOUT_SEC: u64 = 5u64;
+// Keeps datagrams below the typical MTU, so they are not fragmented.
+static MAX_UDP_PAYLOAD: usize = 1400;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct InfluxConfig {
+    /// InfluxDB url; either `http[s]://host:port`, which may include basic auth, or `udp://host:port`
+    pub Url: String,
+    /// Database to write to; required for http
+    pub Database: Option<String>,
+    /// Number of leading metric name segments that form the measurement; the remaining segments form the field.
+    /// Defaults to all but the last segment
+    pub MeasurementSegments: Option<usize>,
+    /// Max number of samples to keep while InfluxDB is unreachable; defaults to 10000
+    pub MaxQueueSize: Option<usize>,
+}
+
+impl InfluxConfig {
+    pub fn apply_defaults(&mut self) {
+        self.MaxQueueSize.get_or_insert(DEFAULT_MAX_QUEUE_SIZE);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.endpoint().map(|_| ())
+    }
+
+    /// Parses `Url` into the http `/write` url or the UDP address samples are written to.
+    fn endpoint(&self) -> Result<Endpoint, ConfigError> {
+        let url = Url::parse(&self.Url)
+            .map_err(|e| ConfigError::invalid("Influx", "Url", format!("'{}' is not a valid url: {}", utils::redact_url(&self.Url), e)))?;
+        match url.scheme() {
+            "http" | "https" => {
+                let database = self.Database.as_ref()
+                    .ok_or_else(|| ConfigError::invalid("Influx", "Database", "a database is required for http"))?;
+                write_url(&url, database).map(Endpoint::Http).map_err(|e| ConfigError::invalid("Influx", "Url", e))
+            }
+            "udp" => match (url.host_str(), url.port()) {
+                (Some(host), Some(port)) => Ok(Endpoint::Udp(format!("{}:{}", host, port))),
+                _ => Err(ConfigError::invalid("Influx", "Url", "a udp url requires a host and a port")),
+            },
+            scheme => Err(ConfigError::invalid("Influx", "Url", format!("unsupported scheme '{}'; use http, https, or udp", scheme))),
+        }
+    }
+}
+
+enum Endpoint {
+    Http(Url),
+    Udp(String),
+}
+
+enum Transport {
+    Http { client: reqwest::Client, url: Url },
+    Udp { socket: UdpSocket, addr: String },
+}
+
+/// Writes samples as InfluxDB line protocol either via http to the `/write` endpoint or as UDP datagrams.
+pub struct Influx {
+    id: Id,
+    transport: Transport,
+    hostname: String,
+    default_tags: Tags,
+    measurement_segments: Option<usize>,
+    max_queue_size: usize,
+    queue: Vec<Sample>,
+    circuit_breaker: CircuitBreaker,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Emitter + Send>>, ConfigError> {
+    match config.Influx {
+        Some(ref influx_config) => {
+            let id = format!("influx@{}", utils::redact_url(&influx_config.Url));
+            let emitter = Influx {
+                id: id.clone(),
+                transport: create_transport(influx_config)?,
+                hostname: config.Hostname.clone(),
+                default_tags: config.Tags.clone(),
+                measurement_segments: influx_config.MeasurementSegments,
+                max_queue_size: influx_config.MaxQueueSize.unwrap_or(DEFAULT_MAX_QUEUE_SIZE),
+                queue: Vec::new(),
+                circuit_breaker: CircuitBreaker::new(format!("InfluxDB at {}", utils::redact_url(&influx_config.Url))),
+            };
+            info!("Created instance of InfluxDB emitter: {}", id);
+            Ok(vec![Box::new(emitter)])
+        }
+        None => {
+            Ok(Vec::new())
+        }
+    }
+}
+
+fn create_transport(config: &InfluxConfig) -> Result<Transport, ConfigError> {
+    match config.endpoint()? {
+        Endpoint::Http(url) => {
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(SEND_TIMEOUT_SEC))
+                .build()
+                .map_err(|e| ConfigError::invalid("Influx", "Url", format!("failed to build http client: {}", e)))?;
+            Ok(Transport::Http { client, url })
+        }
+        Endpoint::Udp(addr) => {
+            let socket = UdpSocket::bind("0.0.0.0:0")
+                .map_err(|e| ConfigError::invalid("Influx", "Url", format!("failed to bind udp socket: {}", e)))?;
+            Ok(Transport::Udp { socket, addr })
+        }
+    }
+}
+
+/// Returns the url of the `/write` endpoint below the url's path, e.g., `http://host:8086/influx/write?db=metrics`.
+fn write_url(url: &Url, database: &str) -> Result<Url, String> {
+    let mut url = url.clone();
+    // Joining a relative path replaces the last path segment unless the path ends with a slash.
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    let mut write_url = url.join("write").map_err(|e| e.to_string())?;
+    write_url.query_pairs_mut().append_pair("db", database);
+
+    Ok(write_url)
+}
+
+impl Influx {
+    fn send_queue(&mut self) -> Result<(), String> {
+        // Keeps the queue index of each line, so samples of datagrams that have been sent can be removed on failure.
+        let lines: Vec<(usize, String)> = self.queue.iter().enumerate()
+            .filter_map(|(i, s)| format_line(s, &self.hostname, &self.default_tags, self.measurement_segments).map(|l| (i, l)))
+            .collect();
+
+        match self.transport {
+            Transport::Http { ref client, ref url } => {
+                let body: String = lines.iter().map(|(_, line)| line.as_str()).collect();
+                let req = client.post(url.clone()).body(body);
+                let req = match (url.username(), url.password()) {
+                    (username, Some(password)) if !username.is_empty() => req.basic_auth(username, Some(password)),
+                    _ => req,
+                };
+                match req.send() {
+                    Ok(ref response) if response.status() == StatusCode::NO_CONTENT => {},
+                    Ok(ref response) if response.status().is_client_error() => {
+                        // InfluxDB will reject these samples again, so they are dropped.
+                        error!("InfluxDB rejected {} samples, because {}. Dropping samples.",
+                               lines.len(), response.status());
+                    },
+                    Ok(response) => return Err(format!("{}", response.status())),
+                    Err(err) => return Err(format!("{}", err)),
+                }
+            }
+            Transport::Udp { ref socket, ref addr } => {
+                let mut sent = 0;
+                for (payload, len) in packets(&lines, MAX_UDP_PAYLOAD) {
+                    if let Err(err) = socket.send_to(payload.as_bytes(), addr.as_str()) {
+                        // Datagrams are sent only once; the samples of all datagrams sent so far leave the queue.
+                        if sent > 0 {
+                            self.queue.drain(..=lines[sent - 1].0);
+                        }
+                        return Err(err.to_string());
+                    }
+                    sent += len;
+                }
+            }
+        }
+        debug!("Sent {} samples to {}.", lines.len(), self.id);
+        self.queue.clear();
+
+        Ok(())
+    }
+
+    fn trim_queue(&mut self) {
+        let dropped = drop_oldest(&mut self.queue, self.max_queue_size);
+        if dropped > 0 {
+            warn!("Dropping {} oldest samples, because {} is unreachable.", dropped, self.id);
+        }
+    }
+}
+
+impl Emitter for Influx {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn emit_metadata(&mut self, _: &Metadata) {
+        // InfluxDB's line protocol has no notion of metadata.
+    }
+
+    fn emit_sample(&mut self, sample: Sample) {
+        self.queue.push(sample);
+    }
+
+    fn flush(&mut self) {
+        if self.queue.is_empty() || !self.circuit_breaker.allows_request(Instant::now()) {
+            self.trim_queue();
+            return;
+        }
+
+        match self.send_queue() {
+            Ok(_) => self.circuit_breaker.on_success(),
+            Err(err) => {
+                self.circuit_breaker.on_failure(Instant::now(), &err);
+                self.trim_queue();
+            }
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.flush();
+        if !self.queue.is_empty() {
+            warn!("Dropping {} unsent samples for {}.", self.queue.len(), self.id);
+        }
+    }
+
+    fn reload(&mut self, config: &Config) {
+        self.hostname = config.Hostname.clone();
+        self.default_tags = config.Tags.clone();
+    }
+}
+
+/// Splits a dotted metric name into measurement and field. If `measurement_segments` is set, the first segments form
+/// the measurement and the remaining the field; otherwise the last segment is the field. Metric names without a field
+/// segment use the field `value`.
+fn split_metric(metric: &str, measurement_segments: Option<usize>) -> (String, String) {
+    let segments: Vec<&str> = metric.split('.').collect();
+    let n = measurement_segments.unwrap_or(segments.len().saturating_sub(1)).max(1);
+    if n >= segments.len() {
+        (metric.to_string(), DEFAULT_FIELD_NAME.to_string())
+    } else {
+        (segments[..n].join("."), segments[n..].join("."))
+    }
+}
+
+/// Formats a sample as `<measurement>,<tag_key>=<tag_value>,... <field_key>=<field_value> <timestamp>` line. Tags are
+/// sorted as recommended by InfluxDB. The timestamp is given in nanoseconds, InfluxDB's default precision for http and
+/// UDP. Returns `None` for values InfluxDB cannot store.
+fn format_line(s: &Sample, hostname: &str, default_tags: &Tags, measurement_segments: Option<usize>)
+    -> Option<String> {
+    if !s.value.is_finite() {
+        return None;
+    }
+    let (measurement, field) = split_metric(&s.metric, measurement_segments);
+    let tags = tags_with_defaults(s, hostname, default_tags);
+    let mut tags: Vec<String> = tags.iter()
+        .filter(|&(_, v)| !v.is_empty())
+        .map(|(k, v)| format!("{}={}", escape(k, ",= "), escape(v, ",= ")))
+        .collect();
+    tags.sort();
+
+    Some(format!("{},{} {}={} {}\n", escape(&measurement, ", "), tags.join(","), escape(&field, ",= "), s.value, s.time * 1_000_000_000))
+}
+
+fn escape(s: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Joins lines into payloads of at most `max_size` bytes together with their number of lines; a single line longer
+/// than `max_size` forms its own payload.
+fn packets(lines: &[(usize, String)], max_size: usize) -> Vec<(String, usize)> {
+    let mut packets = Vec::new();
+    let mut current = String::new();
+    let mut len = 0;
+    for (_, line) in lines {
+        if !current.is_empty() && current.len() + line.len() > max_size {
+            packets.push((current, len));
+            current = String::new();
+            len = 0;
+        }
+        current.push_str(line);
+        len += 1;
+    }
+    if !current.is_empty() {
+        packets.push((current, len));
+    }
+
+    packets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_metric_by_last_segment_or_configured_segments() {
+        assert_eq!(split_metric("galera.wsrep.local.send.queue", None),
+                   ("galera.wsrep.local.send".to_string(), "queue".to_string()));
+        assert_eq!(split_metric("galera.wsrep.local.send.queue", Some(2)),
+                   ("galera.wsrep".to_string(), "local.send.queue".to_string()));
+        assert_eq!(split_metric("uptime", None), ("uptime".to_string(), "value".to_string()));
+        assert_eq!(split_metric("mongo.uptime", Some(5)), ("mongo.uptime".to_string(), "value".to_string()));
+    }
+
+    #[test]
+    fn format_line_with_escaped_tags() {
+        let mut tags = Tags::new();
+        tags.insert("jvm_name".to_string(), "tomcat server".to_string());
+        let sample = Sample { time: 10, metric: "jvm.gc.stats.full_gc_events".to_string(), value: 1.5, tags: tags };
+
+        let line = format_line(&sample, "webserver", &Tags::new(), None);
+
+        assert_eq!(line,
+                   Some("jvm.gc.stats,host=webserver,jvm_name=tomcat\\ server full_gc_events=1.5 10000000000\n".to_string()));
+    }
+
+    #[test]
+    fn packets_respect_max_size() {
+        let lines = vec![(0, "aaaa\n".to_string()), (1, "bbbb\n".to_string()), (2, "cccc\n".to_string())];
+
+        let packets = packets(&lines, 10);
+
+        assert_eq!(packets, vec![("aaaa\nbbbb\n".to_string(), 2), ("cccc\n".to_string(), 1)]);
+    }
+
+    #[test]
+    fn write_url_keeps_base_path() {
+        let write_url = |url: &str| write_url(&Url::parse(url).unwrap(), "metrics").unwrap().to_string();
+
+        assert_eq!(write_url("http://localhost:8086"), "http://localhost:8086/write?db=metrics");
+        assert_eq!(write_url("http://localhost:8086/influx"), "http://localhost:8086/influx/write?db=metrics");
+        assert_eq!(write_url("http://localhost:8086/influx/"), "http://localhost:8086/influx/write?db=metrics");
+    }
+}
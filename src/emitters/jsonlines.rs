@@ -0,0 +1,265 @@
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::config::{Config, ConfigError};
+use crate::emitters::*;
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+static STDOUT_PATH: &'static str = "-";
+static DEFAULT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+static DEFAULT_MAX_FILES: usize = 5;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct JsonLinesConfig {
+    /// File to write to; omit or use `-` for stdout
+    pub Path: Option<String>,
+    /// Size in bytes after which the file is rotated; defaults to 100 MB
+    pub MaxSize: Option<u64>,
+    /// Number of rotated files to keep; defaults to 5
+    pub MaxFiles: Option<usize>,
+}
+
+impl JsonLinesConfig {
+    pub fn apply_defaults(&mut self) {
+        self.MaxSize.get_or_insert(DEFAULT_MAX_SIZE);
+        self.MaxFiles.get_or_insert(DEFAULT_MAX_FILES);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.Path.as_ref().map_or(false, |path| path.is_empty()) {
+            return Err(ConfigError::invalid("JsonLines", "Path", "must not be empty; omit or use '-' for stdout"));
+        }
+        if self.MaxSize == Some(0) {
+            return Err(ConfigError::invalid("JsonLines", "MaxSize", "must be greater than zero"));
+        }
+
+        Ok(())
+    }
+}
+
+enum Output {
+    Stdout,
+    File(RotatingFile),
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    writer: BufWriter<File>,
+    size: u64,
+}
+
+/// Writes every sample and metadata as one JSON object per line to stdout or a rotating file, e.g., for debugging or
+/// archiving.
+pub struct JsonLines {
+    id: Id,
+    hostname: String,
+    default_tags: Tags,
+    output: Output,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Emitter + Send>>, ConfigError> {
+    match config.JsonLines {
+        Some(ref jsonlines_config) => {
+            let path = jsonlines_config.Path.clone().unwrap_or_else(|| STDOUT_PATH.to_string());
+            let id = format!("jsonlines@{}", path);
+            let output = if path == STDOUT_PATH {
+                Output::Stdout
+            } else {
+                let max_size = jsonlines_config.MaxSize.unwrap_or(DEFAULT_MAX_SIZE);
+                let max_files = jsonlines_config.MaxFiles.unwrap_or(DEFAULT_MAX_FILES);
+                let file = RotatingFile::open(PathBuf::from(&path), max_size, max_files)
+                    .map_err(|e| ConfigError::invalid("JsonLines", "Path", format!("failed to open '{}': {}", path, e)))?;
+                Output::File(file)
+            };
+            let emitter = JsonLines {
+                id: id.clone(),
+                hostname: config.Hostname.clone(),
+                default_tags: config.Tags.clone(),
+                output,
+            };
+            info!("Created instance of JSON lines emitter: {}", id);
+
+            Ok(vec![Box::new(emitter)])
+        }
+        None => {
+            Ok(Vec::new())
+        }
+    }
+}
+
+impl JsonLines {
+    fn write_line(&mut self, json: Value) {
+        let line = format!("{}\n", json);
+        let result = match self.output {
+            Output::Stdout => io::stdout().write_all(line.as_bytes()),
+            Output::File(ref mut file) => file.write_line(&line),
+        };
+        if let Err(err) = result {
+            error!("Failed to write JSON line to {}, because {}.", self.id, err);
+        }
+    }
+}
+
+impl Emitter for JsonLines {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn emit_metadata(&mut self, metadata: &Metadata) {
+        let json = metadata_to_json(metadata);
+        self.write_line(json);
+    }
+
+    fn emit_sample(&mut self, sample: Sample) {
+        let json = sample_to_json(&sample, &self.hostname, &self.default_tags);
+        self.write_line(json);
+    }
+
+    fn flush(&mut self) {
+        let result = match self.output {
+            Output::Stdout => io::stdout().flush(),
+            Output::File(ref mut file) => file.writer.flush(),
+        };
+        if let Err(err) = result {
+            error!("Failed to flush {}, because {}.", self.id, err);
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.flush();
+    }
+
+    fn reload(&mut self, config: &Config) {
+        self.hostname = config.Hostname.clone();
+        self.default_tags = config.Tags.clone();
+    }
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_files: usize) -> io::Result<RotatingFile> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile { path, max_size, max_files, writer: BufWriter::new(file), size })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.writer.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// Renames `path` to `path.1`, `path.1` to `path.2` and so on, dropping the oldest file, and opens a new `path`.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(&self.path, self.max_files));
+            for i in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, i);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, i + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        debug!("Rotated '{}'.", self.path.display());
+
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.writer = BufWriter::new(file);
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_os_string();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+// Keys are inserted in sorted order because serde_json preserves insertion order when a dependency enables its
+// 'preserve_order' feature.
+fn sample_to_json(sample: &Sample, hostname: &str, default_tags: &Tags) -> Value {
+    let tags = tags_with_defaults(sample, hostname, default_tags).into_iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
+    let mut obj = Map::new();
+    obj.insert("metric".to_string(), Value::String(sample.metric.clone()));
+    obj.insert("tags".to_string(), Value::Object(tags));
+    obj.insert("time".to_string(), Value::from(sample.time));
+    obj.insert("type".to_string(), Value::String("sample".to_string()));
+    obj.insert("value".to_string(), Value::from(sample.value));
+
+    Value::Object(obj)
+}
+
+fn metadata_to_json(metadata: &Metadata) -> Value {
+    let mut obj = Map::new();
+    obj.insert("description".to_string(), Value::String(metadata.description.clone()));
+    obj.insert("metric".to_string(), Value::String(metadata.metric.clone()));
+    obj.insert("rate".to_string(), Value::String(metadata.rate.clone()));
+    obj.insert("type".to_string(), Value::String("metadata".to_string()));
+    obj.insert("unit".to_string(), Value::String(metadata.unit.clone()));
+
+    Value::Object(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bosun::Rate;
+
+    use mktemp::Temp;
+    use std::io::Read;
+
+    fn read(path: &Path) -> String {
+        let mut content = String::new();
+        File::open(path).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn sample_and_metadata_as_json() {
+        let sample = Sample { time: 10, metric: "rs-collector.version".to_string(), value: 1.5, tags: Tags::new() };
+        let metadata = Metadata::new("rs-collector.version", Rate::Gauge, "Version", "Version of rs-collector");
+
+        assert_eq!(format!("{}", sample_to_json(&sample, "webserver", &Tags::new())),
+                   r#"{"metric":"rs-collector.version","tags":{"host":"webserver"},"time":10,"type":"sample","value":1.5}"#);
+        assert_eq!(format!("{}", metadata_to_json(&metadata)),
+                   r#"{"description":"Version of rs-collector","metric":"rs-collector.version","rate":"gauge","type":"metadata","unit":"Version"}"#);
+    }
+
+    #[test]
+    fn rotate_file_and_keep_max_files() {
+        let dir = Temp::new_dir().unwrap();
+        let path = dir.to_path_buf().join("samples.jsonl");
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+
+        for line in &["line 1\n", "line 2\n", "line 3\n", "line 4\n"] {
+            file.write_line(line).unwrap();
+        }
+        file.writer.flush().unwrap();
+
+        assert_eq!(read(&path), "line 4\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "line 3\n");
+        assert_eq!(read(&rotated_path(&path, 2)), "line 2\n");
+        assert_eq!(rotated_path(&path, 3).exists(), false);
+    }
+}
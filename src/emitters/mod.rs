@@ -0,0 +1,80 @@
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::config::{Config, ConfigError};
+
+pub type Id = String;
+
+/// An output backend samples and metadata are sent to. The scheduler fans each sample and metadata out to all
+/// configured emitters; every emitter runs in its own thread, so a slow backend does not hold up the others.
+pub trait Emitter {
+    fn id(&self) -> &Id;
+    fn emit_metadata(&mut self, metadata: &Metadata);
+    // Emitters may queue samples until the next call of `flush`.
+    fn emit_sample(&mut self, sample: Sample);
+    // Called every tick to transmit queued samples.
+    fn flush(&mut self);
+    // Called once before the emitter thread finishes; queued samples should be flushed or persisted.
+    fn shutdown(&mut self);
+    // Called when the config has been reloaded; applies the host name and default tags without losing queued samples.
+    fn reload(&mut self, config: &Config);
+    fn get_tick_interval(&self) -> u64 { 15 }
+}
+
+/// Creates the emitters of a config that has been checked by `Config::validate`; errors are returned for outputs that
+/// cannot be set up, e.g., because a port is in use or a file cannot be opened.
+pub fn create_emitters(config: &Config) -> Result<Vec<Box<dyn Emitter + Send>>, ConfigError> {
+    let mut emitters = Vec::new();
+
+    // Create Bosun emitter instance
+    let mut bosun = bosun::create_instances(config)?;
+    emitters.append(&mut bosun);
+
+    // Create Graphite emitter instance
+    let mut graphite = graphite::create_instances(config)?;
+    emitters.append(&mut graphite);
+
+    // Create InfluxDB emitter instance
+    let mut influx = influx::create_instances(config)?;
+    emitters.append(&mut influx);
+
+    // Create JSON lines emitter instance
+    let mut jsonlines = jsonlines::create_instances(config)?;
+    emitters.append(&mut jsonlines);
+
+    // Create OpenTSDB emitter instance
+    let mut opentsdb = opentsdb::create_instances(config)?;
+    emitters.append(&mut opentsdb);
+
+    // Create Prometheus emitter instance
+    let mut prometheus = prometheus::create_instances(config)?;
+    emitters.append(&mut prometheus);
+
+    Ok(emitters)
+}
+
+/// Returns the tags of a sample extended by the host tag and the default tags every metric is sent with.
+pub fn tags_with_defaults(s: &Sample, hostname: &str, default_tags: &Tags) -> Tags {
+    let mut tags = s.tags.clone();
+    tags.insert("host".to_string(), hostname.to_string());
+    tags.extend(default_tags.clone());
+    tags
+}
+
+/// Drops the oldest entries from a queue of an unreachable output until it holds at most `max_len` entries; returns
+/// the number of dropped entries.
+pub fn drop_oldest<T>(queue: &mut Vec<T>, max_len: usize) -> usize {
+    if queue.len() <= max_len {
+        return 0;
+    }
+    let dropped = queue.len() - max_len;
+    queue.drain(..dropped);
+
+    dropped
+}
+
+pub mod bosun;
+pub mod graphite;
+pub mod influx;
+pub mod jsonlines;
+pub mod opentsdb;
+pub mod prometheus;
+pub mod tcp;
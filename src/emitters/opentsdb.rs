@@ -0,0 +1,129 @@
+// See http://opentsdb.net/docs/build/html/api_telnet/put.html
+
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::config::{Config, ConfigError};
+use crate::emitters::*;
+use crate::emitters::tcp::TcpLineSender;
+
+static DEFAULT_PORT: u16 = 4242;
+static DEFAULT_MAX_QUEUE_SIZE: usize = 10000;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct OpenTsdbConfig {
+    /// OpenTSDB or tsdb relay host name and port; the port defaults to 4242
+    pub Host: String,
+    /// Max number of samples to keep while OpenTSDB is unreachable; defaults to 10000
+    pub MaxQueueSize: Option<usize>,
+}
+
+impl OpenTsdbConfig {
+    pub fn apply_defaults(&mut self) {
+        self.MaxQueueSize.get_or_insert(DEFAULT_MAX_QUEUE_SIZE);
+    }
+}
+
+/// Sends samples using OpenTSDB's telnet-style `put` protocol over a persistent TCP connection.
+pub struct OpenTsdb {
+    id: Id,
+    hostname: String,
+    default_tags: Tags,
+    sender: TcpLineSender,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Emitter + Send>>, ConfigError> {
+    match config.OpenTsdb {
+        Some(ref opentsdb_config) => {
+            let host = if opentsdb_config.Host.contains(':') {
+                opentsdb_config.Host.clone()
+            } else {
+                format!("{}:{}", opentsdb_config.Host, DEFAULT_PORT)
+            };
+            let max_queue_size = opentsdb_config.MaxQueueSize.unwrap_or(DEFAULT_MAX_QUEUE_SIZE);
+            let emitter = OpenTsdb {
+                id: format!("opentsdb@{}", host),
+                hostname: config.Hostname.clone(),
+                default_tags: config.Tags.clone(),
+                sender: TcpLineSender::new("OpenTSDB", &host, max_queue_size),
+            };
+            info!("Created instance of OpenTSDB emitter: {}", emitter.id);
+
+            Ok(vec![Box::new(emitter)])
+        }
+        None => {
+            Ok(Vec::new())
+        }
+    }
+}
+
+impl Emitter for OpenTsdb {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn emit_metadata(&mut self, _: &Metadata) {
+        // OpenTSDB's telnet protocol has no notion of metadata.
+    }
+
+    fn emit_sample(&mut self, sample: Sample) {
+        match format_put(&sample, &self.hostname, &self.default_tags) {
+            Some(line) => self.sender.push(line),
+            None => debug!("Skipping sample '{}' with non-finite value {}.", sample.metric, sample.value),
+        }
+    }
+
+    fn flush(&mut self) {
+        self.sender.flush();
+    }
+
+    fn shutdown(&mut self) {
+        self.sender.shutdown();
+    }
+
+    fn reload(&mut self, config: &Config) {
+        self.hostname = config.Hostname.clone();
+        self.default_tags = config.Tags.clone();
+    }
+}
+
+/// Formats a sample as `put <metric> <timestamp> <value> <tagk1=tagv1 ...>` line. Tags are sorted to get stable
+/// lines. Returns `None` for values OpenTSDB cannot store.
+fn format_put(s: &Sample, hostname: &str, default_tags: &Tags) -> Option<String> {
+    if !s.value.is_finite() {
+        return None;
+    }
+    let tags = tags_with_defaults(s, hostname, default_tags);
+    let mut tags: Vec<String> = tags.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    tags.sort();
+
+    Some(format!("put {} {} {} {}\n", s.metric, s.time, s.value, tags.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_put_line_with_host_and_default_tags() {
+        let mut tags = Tags::new();
+        tags.insert("jvm_name".to_string(), "tomcat".to_string());
+        let sample = Sample { time: 10, metric: "jvm.gc.stats.full_gc_events".to_string(), value: 1.5, tags: tags };
+        let mut default_tags = Tags::new();
+        default_tags.insert("domain".to_string(), "example.com".to_string());
+
+        let line = format_put(&sample, "webserver", &default_tags);
+
+        assert_eq!(line,
+                   Some("put jvm.gc.stats.full_gc_events 10 1.5 domain=example.com host=webserver jvm_name=tomcat\n"
+                        .to_string()));
+    }
+
+    #[test]
+    fn format_put_skips_non_finite_values() {
+        let sample = Sample { time: 10, metric: "test.metric".to_string(), value: ::std::f64::NAN, tags: Tags::new() };
+
+        assert_eq!(format_put(&sample, "webserver", &Tags::new()), None);
+    }
+}
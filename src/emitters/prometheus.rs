@@ -0,0 +1,325 @@
+// See https://prometheus.io/docs/instrumenting/exposition_formats/
+
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::config::{Config, ConfigError};
+use crate::emitters::*;
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::ToSocketAddrs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Response, Server};
+
+static DEFAULT_STALE_AFTER_SEC: u64 = 300;
+static METRICS_PATH: &'static str = "/metrics";
+static CONTENT_TYPE: &'static str = "text/plain; version=0.0.4; charset=utf-8";
+static RECV_TIMEOUT_MS: u64 = 500;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct PrometheusConfig {
+    /// Address and port to serve `/metrics` on, e.g., `0.0.0.0:9163`
+    pub Listen: String,
+    /// Number of seconds after which a series that has not been updated is removed; defaults to 300
+    pub StaleAfter: Option<u64>,
+}
+
+impl PrometheusConfig {
+    pub fn apply_defaults(&mut self) {
+        self.StaleAfter.get_or_insert(DEFAULT_STALE_AFTER_SEC);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.Listen.to_socket_addrs()
+            .map(|_| ())
+            .map_err(|e| ConfigError::invalid("Prometheus", "Listen", format!("'{}' is not a valid address: {}", self.Listen, e)))
+    }
+}
+
+type Labels = BTreeMap<String, String>;
+
+struct Series {
+    value: f64,
+    updated: Instant,
+}
+
+/// Most recent value of every series plus the metadata of all metrics, shared between the emitter and the http server.
+#[derive(Default)]
+struct Registry {
+    metadata: HashMap<String, Metadata>,
+    series: BTreeMap<String, BTreeMap<Labels, Series>>,
+}
+
+/// Serves the most recent value of every sample on an http endpoint in Prometheus text format.
+pub struct Prometheus {
+    id: Id,
+    hostname: String,
+    default_tags: Tags,
+    stale_after: Duration,
+    registry: Arc<Mutex<Registry>>,
+    shutdown: Arc<AtomicBool>,
+    server_thread: Option<JoinHandle<()>>,
+}
+
+pub fn create_instances(config: &Config) -> Result<Vec<Box<dyn Emitter + Send>>, ConfigError> {
+    match config.Prometheus {
+        Some(ref prometheus_config) => {
+            let id = format!("prometheus@{}", prometheus_config.Listen);
+            let server = Server::http(prometheus_config.Listen.as_str())
+                .map_err(|e| ConfigError::invalid("Prometheus", "Listen",
+                                                  format!("failed to listen on '{}': {}", prometheus_config.Listen, e)))?;
+
+            let registry = Arc::new(Mutex::new(Registry::default()));
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let server_thread = spawn_server(server, registry.clone(), shutdown.clone());
+            let emitter = Prometheus {
+                id: id.clone(),
+                hostname: config.Hostname.clone(),
+                default_tags: config.Tags.clone(),
+                stale_after: Duration::from_secs(prometheus_config.StaleAfter.unwrap_or(DEFAULT_STALE_AFTER_SEC)),
+                registry,
+                shutdown,
+                server_thread: Some(server_thread),
+            };
+            info!("Created instance of Prometheus emitter: {}", id);
+
+            Ok(vec![Box::new(emitter)])
+        }
+        None => {
+            Ok(Vec::new())
+        }
+    }
+}
+
+fn spawn_server(server: Server, registry: Arc<Mutex<Registry>>, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        info!("Prometheus server thread started; listening on {}.", server.server_addr());
+        while !shutdown.load(Ordering::Relaxed) {
+            let request = match server.recv_timeout(Duration::from_millis(RECV_TIMEOUT_MS)) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(err) => {
+                    error!("Prometheus server failed to receive request, because {}.", err);
+                    continue;
+                }
+            };
+
+            // Scrapers may add query parameters, e.g., `/metrics?name[]=up`, which are ignored.
+            let path = request.url().split('?').next().unwrap_or("");
+            let response = if path == METRICS_PATH {
+                let body = registry.lock().unwrap().render();
+                let content_type = Header::from_bytes(&b"Content-Type"[..], CONTENT_TYPE.as_bytes()).unwrap();
+                Response::from_string(body).with_header(content_type)
+            } else {
+                Response::from_string("Not Found").with_status_code(404)
+            };
+            if let Err(err) = request.respond(response) {
+                debug!("Prometheus server failed to respond, because {}.", err);
+            }
+        }
+        info!("Prometheus server thread finished.");
+    })
+}
+
+impl Emitter for Prometheus {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn emit_metadata(&mut self, metadata: &Metadata) {
+        let mut registry = self.registry.lock().unwrap();
+        registry.metadata.insert(sanitize_metric_name(&metadata.metric), metadata.clone());
+    }
+
+    fn emit_sample(&mut self, sample: Sample) {
+        let labels: Labels = tags_with_defaults(&sample, &self.hostname, &self.default_tags).into_iter()
+            .map(|(k, v)| (sanitize_label_name(&k), v))
+            .collect();
+        let mut registry = self.registry.lock().unwrap();
+        registry.series
+            .entry(sanitize_metric_name(&sample.metric))
+            .or_insert_with(BTreeMap::new)
+            .insert(labels, Series { value: sample.value, updated: Instant::now() });
+    }
+
+    fn flush(&mut self) {
+        let mut registry = self.registry.lock().unwrap();
+        let expired = registry.expire(Instant::now(), self.stale_after);
+        if expired > 0 {
+            debug!("Removed {} stale series from {}.", expired, self.id);
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(server_thread) = self.server_thread.take() {
+            let _ = server_thread.join();
+        }
+    }
+
+    fn reload(&mut self, config: &Config) {
+        self.hostname = config.Hostname.clone();
+        self.default_tags = config.Tags.clone();
+    }
+}
+
+impl Registry {
+    /// Removes all series that have not been updated within `stale_after`; returns the number of removed series.
+    fn expire(&mut self, now: Instant, stale_after: Duration) -> usize {
+        let mut expired = 0;
+        for series in self.series.values_mut() {
+            let len = series.len();
+            series.retain(|_, s| now.duration_since(s.updated) < stale_after);
+            expired += len - series.len();
+        }
+        self.series.retain(|_, series| !series.is_empty());
+
+        expired
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, series) in &self.series {
+            if let Some(metadata) = self.metadata.get(name) {
+                out.push_str(&format!("# HELP {} {}\n", name, escape_help(&metadata.description)));
+                out.push_str(&format!("# TYPE {} {}\n", name, metric_type(&metadata.rate)));
+            }
+            for (labels, s) in series {
+                let labels: Vec<String> = labels.iter()
+                    .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+                    .collect();
+                out.push_str(&format!("{}{{{}}} {}\n", name, labels.join(","), format_value(s.value)));
+            }
+        }
+
+        out
+    }
+}
+
+/// Maps Bosun's rate types to Prometheus' metric types; rates are already per second and thus gauges.
+fn metric_type(rate: &str) -> &'static str {
+    match rate {
+        "counter" => "counter",
+        "gauge" | "rate" => "gauge",
+        _ => "untyped",
+    }
+}
+
+/// Replaces all characters that are not allowed in Prometheus metric names, e.g., dots and dashes, by underscores.
+fn sanitize_metric_name(name: &str) -> String {
+    sanitize(name, |c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn sanitize_label_name(name: &str) -> String {
+    sanitize(name, |c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn sanitize<F: Fn(char) -> bool>(name: &str, is_allowed: F) -> String {
+    let mut sanitized: String = name.chars().map(|c| if is_allowed(c) { c } else { '_' }).collect();
+    if sanitized.chars().next().map_or(true, |c| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+fn escape_help(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0f64 { "+Inf".to_string() } else { "-Inf".to_string() }
+    } else {
+        format!("{}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bosun::Rate;
+
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn labels(pairs: &[(&str, &str)]) -> Labels {
+        pairs.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!(sanitize_metric_name("rs-collector.stats.rss"), "rs_collector_stats_rss");
+        assert_eq!(sanitize_metric_name("0day.count"), "_0day_count");
+        assert_eq!(sanitize_label_name("jvm:name"), "jvm_name");
+    }
+
+    #[test]
+    fn render_with_help_and_type() {
+        let mut registry = Registry::default();
+        registry.metadata.insert("jvm_gc_stats_full_gc_events".to_string(),
+                                 Metadata::new("jvm.gc.stats.full_gc_events", Rate::Counter, "Events", "Full GC \"events\""));
+        let mut series = BTreeMap::new();
+        series.insert(labels(&[("host", "webserver"), ("jvm_name", "tomcat")]), Series { value: 3.0, updated: Instant::now() });
+        registry.series.insert("jvm_gc_stats_full_gc_events".to_string(), series);
+
+        let out = registry.render();
+
+        assert_eq!(out, "# HELP jvm_gc_stats_full_gc_events Full GC \"events\"\n\
+                         # TYPE jvm_gc_stats_full_gc_events counter\n\
+                         jvm_gc_stats_full_gc_events{host=\"webserver\",jvm_name=\"tomcat\"} 3\n");
+    }
+
+    #[test]
+    fn expire_stale_series() {
+        let mut registry = Registry::default();
+        let now = Instant::now();
+        let mut series = BTreeMap::new();
+        series.insert(labels(&[("host", "a")]), Series { value: 1.0, updated: now });
+        series.insert(labels(&[("host", "b")]), Series { value: 2.0, updated: now + Duration::from_secs(200) });
+        registry.series.insert("metric".to_string(), series);
+
+        assert_eq!(registry.expire(now + Duration::from_secs(300), Duration::from_secs(300)), 1);
+        assert_eq!(registry.expire(now + Duration::from_secs(500), Duration::from_secs(300)), 1);
+        assert!(registry.series.is_empty());
+    }
+
+    #[test]
+    fn serve_metrics() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr();
+        let registry = Arc::new(Mutex::new(Registry::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server_thread = spawn_server(server, registry.clone(), shutdown.clone());
+        let mut emitter = Prometheus {
+            id: "prometheus".to_string(),
+            hostname: "webserver".to_string(),
+            default_tags: Tags::new(),
+            stale_after: Duration::from_secs(300),
+            registry,
+            shutdown,
+            server_thread: Some(server_thread),
+        };
+        emitter.emit_sample(Sample::new("rs-collector.version", 1.0));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {}?name[]=rs_collector_version HTTP/1.0\r\n\r\n", METRICS_PATH).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        emitter.shutdown();
+
+        assert!(response.starts_with("HTTP/1.0 200"));
+        assert!(response.ends_with("\r\n\r\nrs_collector_version{host=\"webserver\"} 1\n"));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::circuit_breaker::CircuitBreaker;
+use crate::emitters::drop_oldest;
+
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+static CONNECT_TIMEOUT_SEC: u64 = 5u64;
+static WRITE_TIMEOUT_SEC: u64 = 5u64;
+
+/// Sends lines of a plaintext protocol over a persistent TCP connection. If the connection breaks, lines are kept up to
+/// a max queue size and the connection is re-established with exponential backoff.
+pub struct TcpLineSender {
+    name: String,
+    host: String,
+    max_queue_size: usize,
+    queue: Vec<String>,
+    connection: Option<TcpStream>,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl TcpLineSender {
+    pub fn new<T: Into<String>>(name: T, host: &str, max_queue_size: usize) -> TcpLineSender {
+        let name = name.into();
+        TcpLineSender {
+            circuit_breaker: CircuitBreaker::new(format!("{} at {}", name, host)),
+            name,
+            host: host.to_string(),
+            max_queue_size,
+            queue: Vec::new(),
+            connection: None,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        self.queue.push(line);
+    }
+
+    pub fn flush(&mut self) {
+        if self.queue.is_empty() || !self.circuit_breaker.allows_request(Instant::now()) {
+            self.trim_queue();
+            return;
+        }
+
+        match self.send_queue() {
+            Ok(_) => self.circuit_breaker.on_success(),
+            Err(err) => {
+                // A broken connection cannot be reused; the lines are sent again after reconnecting.
+                self.connection = None;
+                self.circuit_breaker.on_failure(Instant::now(), &err.to_string());
+                self.trim_queue();
+            }
+        }
+    }
+
+    pub fn shutdown(&mut self) {
+        self.flush();
+        if !self.queue.is_empty() {
+            warn!("Dropping {} unsent lines for {} at {}.", self.queue.len(), self.name, self.host);
+        }
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, format!("could not resolve '{}'", self.host));
+        for addr in self.host.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, Duration::from_secs(CONNECT_TIMEOUT_SEC)) {
+                Ok(stream) => {
+                    stream.set_write_timeout(Some(Duration::from_secs(WRITE_TIMEOUT_SEC)))?;
+                    info!("Connected to {} at {}.", self.name, addr);
+                    return Ok(stream);
+                }
+                Err(err) => last_err = err,
+            }
+        }
+
+        Err(last_err)
+    }
+
+    fn send_queue(&mut self) -> io::Result<()> {
+        if self.connection.is_none() {
+            self.connection = Some(self.connect()?);
+        }
+
+        let buf: String = self.queue.concat();
+        if let Some(ref mut connection) = self.connection {
+            connection.write_all(buf.as_bytes())?;
+            connection.flush()?;
+        }
+        debug!("Sent {} lines to {} at {}.", self.queue.len(), self.name, self.host);
+        self.queue.clear();
+
+        Ok(())
+    }
+
+    fn trim_queue(&mut self) {
+        let dropped = drop_oldest(&mut self.queue, self.max_queue_size);
+        if dropped > 0 {
+            warn!("Dropping {} oldest lines, because {} at {} is unreachable.", dropped, self.name, self.host);
+        }
+    }
+}
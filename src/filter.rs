@@ -0,0 +1,195 @@
+//! Drops and renames metrics between collectors and emitters, so that only wanted series are sent.
+
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::bosun::{Metadata, Sample};
+use crate::config::ConfigError;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct FilterConfig {
+    /// Regexes of metric names to keep; all other metrics are dropped
+    pub IncludeMetrics: Option<Vec<String>>,
+    /// Regexes of metric names to drop
+    pub ExcludeMetrics: Option<Vec<String>>,
+    /// Regexes of tag values by tag name; samples with such a tag are only kept if the value matches
+    pub IncludeTags: Option<HashMap<String, String>>,
+    /// Regexes of tag values by tag name; samples with such a tag are dropped if the value matches
+    pub ExcludeTags: Option<HashMap<String, String>>,
+    /// Rules to rename the kept metrics; applied in order
+    pub Rename: Option<Vec<RenameConfig>>,
+    /// Prepended to the metric names after renaming
+    pub Prefix: Option<String>,
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct RenameConfig {
+    /// Regex of the part of metric names to replace
+    pub Pattern: String,
+    /// Replacement that may refer to capture groups of the pattern, e.g., `$1`
+    pub Replacement: String,
+}
+
+/// Compiled `FilterConfig` that is applied to samples and their metadata alike.
+#[derive(Debug, Clone)]
+pub struct MetricFilter {
+    include_metrics: Vec<Regex>,
+    exclude_metrics: Vec<Regex>,
+    include_tags: Vec<(String, Regex)>,
+    exclude_tags: Vec<(String, Regex)>,
+    rename: Vec<(Regex, String)>,
+    prefix: String,
+}
+
+impl MetricFilter {
+    /// Compiles the regexes of the config; `section` names the config section in errors.
+    pub fn new(config: &FilterConfig, section: &str) -> Result<MetricFilter, ConfigError> {
+        let compile = |field: &str, regex: &str| Regex::new(regex)
+            .map_err(|err| ConfigError::invalid(section, field, format!("invalid regex '{}': {}", regex, err)));
+        let compile_all = |field: &str, regexes: &Option<Vec<String>>| regexes.iter().flatten()
+            .map(|regex| compile(field, regex))
+            .collect::<Result<Vec<_>, _>>();
+        let compile_tags = |field: &str, regexes: &Option<HashMap<String, String>>| regexes.iter().flatten()
+            .map(|(tag, regex)| compile(field, regex).map(|regex| (tag.clone(), regex)))
+            .collect::<Result<Vec<_>, _>>();
+
+        Ok(MetricFilter {
+            include_metrics: compile_all("IncludeMetrics", &config.IncludeMetrics)?,
+            exclude_metrics: compile_all("ExcludeMetrics", &config.ExcludeMetrics)?,
+            include_tags: compile_tags("IncludeTags", &config.IncludeTags)?,
+            exclude_tags: compile_tags("ExcludeTags", &config.ExcludeTags)?,
+            rename: config.Rename.iter().flatten()
+                .map(|rule| compile("Rename", &rule.Pattern).map(|regex| (regex, rule.Replacement.clone())))
+                .collect::<Result<Vec<_>, _>>()?,
+            prefix: config.Prefix.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Returns the renamed sample or `None`, if it is dropped.
+    pub fn sample(&self, mut sample: Sample) -> Option<Sample> {
+        let tags = &sample.tags;
+        let included = self.include_tags.iter()
+            .all(|(tag, regex)| tags.get(tag).map_or(true, |value| regex.is_match(value)));
+        let excluded = self.exclude_tags.iter()
+            .any(|(tag, regex)| tags.get(tag).map_or(false, |value| regex.is_match(value)));
+        if !included || excluded {
+            return None;
+        }
+
+        sample.metric = self.metric(&sample.metric)?;
+        Some(sample)
+    }
+
+    /// Returns the renamed metadata or `None`, if its metric is dropped.
+    pub fn metadata(&self, mut metadata: Metadata) -> Option<Metadata> {
+        metadata.metric = self.metric(&metadata.metric)?;
+        Some(metadata)
+    }
+
+    fn metric(&self, metric: &str) -> Option<String> {
+        let included = self.include_metrics.is_empty() || self.include_metrics.iter().any(|regex| regex.is_match(metric));
+        let excluded = self.exclude_metrics.iter().any(|regex| regex.is_match(metric));
+        if !included || excluded {
+            return None;
+        }
+
+        let renamed = self.rename.iter()
+            .fold(metric.to_string(), |metric, (regex, replacement)| regex.replace_all(&metric, replacement.as_str()).into_owned());
+        Some(format!("{}{}", self.prefix, renamed))
+    }
+}
+
+/// Applies the filters in order, e.g., a collector's filter and then the global filter.
+pub fn filter_sample(sample: Sample, filters: &[Option<&MetricFilter>]) -> Option<Sample> {
+    filters.iter().flatten().try_fold(sample, |sample, filter| filter.sample(sample))
+}
+
+/// Applies the filters in order like `filter_sample`.
+pub fn filter_metadata(metadata: Metadata, filters: &[Option<&MetricFilter>]) -> Option<Metadata> {
+    filters.iter().flatten().try_fold(metadata, |metadata, filter| filter.metadata(metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bosun::{Rate, Tags};
+
+    fn filter_config() -> FilterConfig {
+        FilterConfig {
+            IncludeMetrics: None,
+            ExcludeMetrics: None,
+            IncludeTags: None,
+            ExcludeTags: None,
+            Rename: None,
+            Prefix: None,
+        }
+    }
+
+    fn sample(metric: &str, tags: &[(&str, &str)]) -> Sample {
+        let tags: Tags = tags.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect();
+        Sample::new_with_tags(metric, 1f64, tags)
+    }
+
+    #[test]
+    fn include_and_exclude_metrics() {
+        let config = FilterConfig {
+            IncludeMetrics: Some(vec!["^galera\\.".to_string()]),
+            ExcludeMetrics: Some(vec!["\\.local_".to_string()]),
+            ..filter_config()
+        };
+        let filter = MetricFilter::new(&config, "Filter").unwrap();
+
+        assert!(filter.sample(sample("galera.wsrep.cluster_size", &[])).is_some());
+        assert!(filter.sample(sample("galera.wsrep.local_state", &[])).is_none());
+        assert!(filter.sample(sample("mongo.connections.current", &[])).is_none());
+        assert!(filter.metadata(Metadata::new("galera.wsrep.local_state", Rate::Gauge, "", "")).is_none());
+    }
+
+    #[test]
+    fn include_and_exclude_tags() {
+        let config = FilterConfig {
+            IncludeTags: Some(vec![("replicaset".to_string(), "^rs0[12]$".to_string())].into_iter().collect()),
+            ExcludeTags: Some(vec![("db".to_string(), "^test".to_string())].into_iter().collect()),
+            ..filter_config()
+        };
+        let filter = MetricFilter::new(&config, "Filter").unwrap();
+
+        assert!(filter.sample(sample("mongo.ops", &[("replicaset", "rs01")])).is_some());
+        assert!(filter.sample(sample("mongo.ops", &[("replicaset", "rs03")])).is_none());
+        assert!(filter.sample(sample("mongo.ops", &[("db", "test_import")])).is_none());
+        assert!(filter.sample(sample("os.load", &[])).is_some());
+    }
+
+    #[test]
+    fn rename_and_prefix_samples_and_metadata() {
+        let config = FilterConfig {
+            Rename: Some(vec![RenameConfig { Pattern: "^galera\\.wsrep\\.".to_string(), Replacement: "galera.".to_string() }]),
+            Prefix: Some("db.".to_string()),
+            ..filter_config()
+        };
+        let collector_filter = MetricFilter::new(&config, "Galera.Filter").unwrap();
+        let global_config = FilterConfig { ExcludeMetrics: Some(vec!["^galera\\.wsrep\\.".to_string()]), ..filter_config() };
+        let global_filter = MetricFilter::new(&global_config, "Filter").unwrap();
+        let filters = [Some(&collector_filter), Some(&global_filter)];
+
+        let sample = filter_sample(sample("galera.wsrep.cluster_size", &[]), &filters).unwrap();
+        assert_eq!(sample.metric, "db.galera.cluster_size");
+        let metadata = filter_metadata(Metadata::new("galera.wsrep.cluster_size", Rate::Gauge, "", ""), &filters).unwrap();
+        assert_eq!(metadata.metric, "db.galera.cluster_size");
+    }
+
+    #[test]
+    fn reject_invalid_regex() {
+        let config = FilterConfig { ExcludeMetrics: Some(vec!["galera.(".to_string()]), ..filter_config() };
+
+        let err = MetricFilter::new(&config, "Galera.Filter").unwrap_err();
+
+        assert!(err.to_string().starts_with("invalid 'ExcludeMetrics' in section [Galera.Filter]: invalid regex 'galera.('"));
+    }
+}
@@ -7,6 +7,7 @@ extern crate chan_signal;
 #[macro_use]
 extern crate log;
 extern crate env_logger;
+extern crate flate2;
 extern crate get_if_addrs;
 extern crate is_executable;
 extern crate itertools;
@@ -15,15 +16,30 @@ extern crate mongodb;
 extern crate mysql;
 #[cfg(target_os = "linux")]
 extern crate procinfo;
+extern crate rand;
 extern crate regex;
-extern crate rustc_serialize;
+extern crate reqwest;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tiny_http;
 extern crate toml;
+#[cfg(test)]
+extern crate mktemp;
 
 pub mod bosun;
+pub mod check;
+pub mod circuit_breaker;
+pub mod collector_health;
 extern crate chrono;
 pub mod collectors;
 pub mod config;
+pub mod emitters;
+pub mod filter;
+pub mod sanitizer;
 pub mod scheduler;
+pub mod spool;
 pub mod utils;
 
 enum Msg<T> {
@@ -6,13 +6,16 @@ extern crate env_logger;
 extern crate rs_collector;
 extern crate time;
 
-use clap::{Arg, ArgMatches, App};
+use clap::{Arg, ArgMatches, App, SubCommand};
 use log::SetLoggerError;
 use std::env;
 use std::error::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::thread;
 
-use rs_collector::config::Config;
+use rs_collector::collectors::Collector;
+use rs_collector::config::{Config, Format};
+use rs_collector::scheduler::{COLLECTOR_THREAD_NAME_PREFIX, panic_message};
 
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 static DEFAULT_CONFIG_FILE: &'static str = "/etc/rs-collector.conf";
@@ -29,26 +32,49 @@ fn main() {
             .long("config")
             .value_name("FILE")
             .help("Sets a custom config file")
-            .takes_value(true))
+            .takes_value(true)
+            .global(true))
         .arg(Arg::with_name("show-config")
             .long("show-config")
-            .help("Prints config"));
+            .value_name("FORMAT")
+            .help("Prints the effective config with secrets redacted and exits")
+            .takes_value(true)
+            .min_values(0)
+            .max_values(1)
+            .possible_values(&["toml", "json"]))
+        .subcommand(SubCommand::with_name("check-config")
+            .about("Validates the config, initializes each collector, and collects once"));
     let cli_args = app.get_matches();
 
-    let config: Config = match parse_args(&cli_args) {
+    let config_file_path = match config_file_path(&cli_args) {
+        Some(config_file_path) => config_file_path,
+        None => {
+            let path = cli_args.value_of("configfile").unwrap_or(DEFAULT_CONFIG_FILE);
+            exit_with_error(&format!("Failed to parse configuration, because '{}' does not exist.", path), -2);
+        }
+    };
+    let config: Config = match parse_args(&config_file_path) {
         Ok(config) => config,
         Err(err) => {
             exit_with_error(&format!("Failed to parse configuration, because {}.", err), -2);
         }
     };
-    // TODO a config must have been parsed; if not exit!
     if cli_args.is_present("show-config") {
-        println!("config: {:?}", config);
+        let format = cli_args.value_of("show-config").unwrap_or("toml").parse::<Format>()
+            .unwrap_or_else(|err| exit_with_error(&err, -1));
+        match config.show(format) {
+            Ok(shown) => print!("{}", shown),
+            Err(err) => exit_with_error(&format!("Failed to show configuration, because {}.", err), -2),
+        }
+        std::process::exit(0);
     }
 
-    std::panic::set_hook(Box::new(print_panic_and_abort));
+    std::panic::set_hook(Box::new(log_panic_or_abort));
 
-    run(&config);
+    if cli_args.subcommand_matches("check-config").is_some() {
+        self::check_config(config);
+    }
+    run(config, config_file_path);
 }
 
 fn init_logger() -> Result<(), SetLoggerError> {
@@ -77,35 +103,60 @@ fn init_logger() -> Result<(), SetLoggerError> {
     builder.init()
 }
 
-fn parse_args(cli_args: &ArgMatches) -> Result<Config, Box<dyn Error>> {
+/// Returns the path of the config file, if it exists.
+fn config_file_path(cli_args: &ArgMatches) -> Option<PathBuf> {
     let config_file_path = Path::new(cli_args.value_of("configfile").unwrap_or(DEFAULT_CONFIG_FILE));
-    let config: Config = if config_file_path.exists() {
-        let config = r#try!(Config::load_from_rs_collector_config(&config_file_path));
-        config
-    } else {
-        Default::default()
-    };
+    if config_file_path.exists() { Some(config_file_path.to_path_buf()) } else { None }
+}
+
+fn parse_args(config_file_path: &Path) -> Result<Config, Box<dyn Error>> {
+    let config = r#try!(Config::load_from_rs_collector_config(config_file_path));
 
     Ok(config)
 }
 
-fn print_panic_and_abort(info: &std::panic::PanicInfo<'_>) {
-    if let Some(loc) = info.location() {
-        println!("Application panicked at {}", loc);
-    } else {
-        println!("Application panicked");
-    }
+/// Panics of collectors are caught by the scheduler and handled like collection errors, so they are only logged. Any
+/// other panic aborts the application to avoid a dead scheduler or emitter going unnoticed.
+fn log_panic_or_abort(info: &std::panic::PanicInfo<'_>) {
+    let location = info.location().map(|loc| loc.to_string()).unwrap_or_else(|| "unknown location".to_string());
+    let reason = panic_message(info.payload());
 
-    if let Some(msg) = info.payload().downcast_ref::<&str>() {
-        println!("Reason: {}", msg);
+    let current = thread::current();
+    if let Some(name) = current.name().filter(|name| name.starts_with(COLLECTOR_THREAD_NAME_PREFIX)) {
+        error!("Collector thread '{}' panicked at {}, because {}", name, location, reason);
+        return;
     }
 
+    println!("Application panicked at {}", location);
+    println!("Reason: {}", reason);
+
     std::process::abort()
 }
 
-fn run(config: &Config) {
-    let collectors = rs_collector::collectors::create_collectors(config);
-    rs_collector::scheduler::run(collectors, config);
+fn run(config: Config, config_file_path: PathBuf) {
+    let collectors = create_collectors(&config);
+    let emitters = rs_collector::emitters::create_emitters(&config)
+        .unwrap_or_else(|err| exit_with_error(&format!("Failed to create outputs, because {}.", err), -2));
+    rs_collector::scheduler::run(collectors, emitters, config, Some(config_file_path));
+}
+
+/// Checks all configured collectors and exits non-zero if any collector failed.
+fn check_config(config: Config) -> ! {
+    let collectors = create_collectors(&config);
+    let results = rs_collector::check::check_collectors(collectors, &config);
+    print!("{}", rs_collector::check::format_results(&results));
+
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    if failed > 0 {
+        exit_with_error(&format!("{} of {} collectors failed.", failed, results.len()), -3);
+    }
+    println!("All {} collectors passed.", results.len());
+    std::process::exit(0);
+}
+
+fn create_collectors(config: &Config) -> Vec<Box<dyn Collector + Send>> {
+    rs_collector::collectors::create_collectors(config)
+        .unwrap_or_else(|err| exit_with_error(&format!("Failed to create collectors, because {}.", err), -2))
 }
 
 fn exit_with_error(msg: &str, exit_code: i32) -> ! {
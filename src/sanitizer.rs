@@ -0,0 +1,191 @@
+//! Guards outputs against metric names and tags that Bosun and OpenTSDB reject, which would fail whole requests.
+
+use std::collections::HashSet;
+
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::collectors::Id;
+
+/// What to do with samples and metadata whose metric name or tags contain characters that OpenTSDB rejects
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InvalidNamePolicy {
+    /// Replace invalid characters by `_`
+    Sanitize,
+    /// Drop the sample or metadata
+    Reject,
+}
+
+impl Default for InvalidNamePolicy {
+    fn default() -> InvalidNamePolicy {
+        InvalidNamePolicy::Sanitize
+    }
+}
+
+/// Checks that a metric name, tag key, or tag value only consists of characters allowed by OpenTSDB: `a-z`, `A-Z`,
+/// `0-9`, `-`, `_`, `.`, `/`, and Unicode letters.
+pub fn is_valid(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(is_valid_char)
+}
+
+/// Replaces each character not allowed by OpenTSDB by `_`; an empty string becomes `_`.
+pub fn sanitize(s: &str) -> String {
+    if s.is_empty() {
+        return "_".to_string();
+    }
+    s.chars().map(|c| if is_valid_char(c) { c } else { '_' }).collect()
+}
+
+/// Checks that all tag keys and values are valid.
+pub fn validate_tags(tags: &Tags) -> Result<(), String> {
+    for (key, value) in tags {
+        if !is_valid(key) {
+            return Err(format!("tag key '{}' must only contain letters, digits, '-', '_', '.', and '/'", key));
+        }
+        if !is_valid(value) {
+            return Err(format!("value '{}' of tag '{}' must only contain letters, digits, '-', '_', '.', and '/'", value, key));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_valid_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '/'
+}
+
+/// Outcome of checking the names of a sample or metadata
+#[derive(Debug)]
+pub enum Checked<T> {
+    Valid(T),
+    Sanitized(T),
+    Rejected,
+}
+
+impl<T> Checked<T> {
+    pub fn is_invalid(&self) -> bool {
+        match *self {
+            Checked::Valid(_) => false,
+            Checked::Sanitized(_) | Checked::Rejected => true,
+        }
+    }
+
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Checked::Valid(x) | Checked::Sanitized(x) => Some(x),
+            Checked::Rejected => None,
+        }
+    }
+}
+
+/// Applies the `InvalidNames` policy to everything collectors send to the outputs and logs each offending collector
+/// once.
+#[derive(Debug)]
+pub struct Sanitizer {
+    policy: InvalidNamePolicy,
+    reported: HashSet<Id>,
+}
+
+impl Sanitizer {
+    pub fn new(policy: InvalidNamePolicy) -> Sanitizer {
+        Sanitizer { policy, reported: HashSet::new() }
+    }
+
+    pub fn set_policy(&mut self, policy: InvalidNamePolicy) {
+        self.policy = policy;
+    }
+
+    pub fn sample(&mut self, id: &Id, mut sample: Sample) -> Checked<Sample> {
+        let invalid = if !is_valid(&sample.metric) {
+            Some(format!("metric name '{}'", sample.metric))
+        } else {
+            sample.tags.iter()
+                .find(|&(key, value)| !is_valid(key) || !is_valid(value))
+                .map(|(key, value)| format!("tag '{}={}' of metric '{}'", key, value, sample.metric))
+        };
+        let invalid = match invalid {
+            Some(invalid) => invalid,
+            None => return Checked::Valid(sample),
+        };
+
+        self.report(id, &invalid);
+        match self.policy {
+            InvalidNamePolicy::Sanitize => {
+                sample.metric = sanitize(&sample.metric);
+                sample.tags = sample.tags.iter().map(|(key, value)| (sanitize(key), sanitize(value))).collect::<Tags>();
+                Checked::Sanitized(sample)
+            }
+            InvalidNamePolicy::Reject => Checked::Rejected,
+        }
+    }
+
+    pub fn metadata(&mut self, id: &Id, mut metadata: Metadata) -> Checked<Metadata> {
+        if is_valid(&metadata.metric) {
+            return Checked::Valid(metadata);
+        }
+
+        self.report(id, &format!("metric name '{}'", metadata.metric));
+        match self.policy {
+            InvalidNamePolicy::Sanitize => {
+                metadata.metric = sanitize(&metadata.metric);
+                Checked::Sanitized(metadata)
+            }
+            InvalidNamePolicy::Reject => Checked::Rejected,
+        }
+    }
+
+    fn report(&mut self, id: &Id, invalid: &str) {
+        if self.reported.insert(id.clone()) {
+            let action = match self.policy {
+                InvalidNamePolicy::Sanitize => "sanitizing",
+                InvalidNamePolicy::Reject => "rejecting",
+            };
+            warn!("Collector {} sent invalid {}; {} this and all further invalid samples of this collector without notice.",
+                  id, invalid, action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(metric: &str, tag: (&str, &str)) -> Sample {
+        let mut tags = Tags::new();
+        tags.insert(tag.0.to_string(), tag.1.to_string());
+        Sample::new_with_tags(metric, 1f64, tags)
+    }
+
+    #[test]
+    fn sanitize_replaces_invalid_characters() {
+        assert_eq!(sanitize("galera#root@localhost"), "galera_root_localhost");
+        assert_eq!(sanitize("köln/süd-1.a_b"), "köln/süd-1.a_b");
+        assert_eq!(sanitize(""), "_");
+        assert!(is_valid(&sanitize("SN 12:34")));
+    }
+
+    #[test]
+    fn sanitize_invalid_samples() {
+        let mut sanitizer = Sanitizer::new(InvalidNamePolicy::Sanitize);
+        let id = "megaraid#0".to_string();
+
+        let checked = sanitizer.sample(&id, sample("hw.storage.drivestats.media_errors", ("serial_number", "SN 12:34")));
+        assert!(checked.is_invalid());
+        assert_eq!(checked.into_option().unwrap().tags["serial_number"], "SN_12_34");
+
+        let checked = sanitizer.sample(&id, sample("hw.storage.drivestats.media_errors", ("serial_number", "SN1234")));
+        assert!(!checked.is_invalid());
+    }
+
+    #[test]
+    fn reject_invalid_samples_and_metadata() {
+        let mut sanitizer = Sanitizer::new(InvalidNamePolicy::Reject);
+        let id = "relay@127.0.0.1:4242".to_string();
+
+        assert!(sanitizer.sample(&id, sample("app requests", ("host", "web01"))).into_option().is_none());
+        assert!(sanitizer.sample(&id, sample("app.requests", ("path", "/api?q=1"))).into_option().is_none());
+        assert!(sanitizer.sample(&id, sample("app.requests", ("path", "/api"))).into_option().is_some());
+        let metadata = Metadata::new("app requests", crate::bosun::Rate::Counter, "", "");
+        assert!(sanitizer.metadata(&id, metadata).into_option().is_none());
+    }
+}
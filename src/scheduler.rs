@@ -2,62 +2,94 @@ use chan::{Sender, Receiver};
 use chan;
 use chan_signal::Signal;
 use chan_signal;
+use rand::{self, Rng};
 
+use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::thread::JoinHandle;
 use std::thread;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::Msg;
+use crate::collector_health::CollectorHealth;
 use crate::config::Config;
-use crate::collectors::{Collector, Error};
+use crate::collectors::{self, Collector, Error};
 use crate::collectors::Id;
-use crate::bosun::{Bosun, BosunRequest, Metadata, Sample};
+use crate::collectors::rscollector::{RS_COLLECTOR_COLLECTOR_TIMEOUTS_METRICNAME, RS_COLLECTOR_COLLECTOR_STATE_METRICNAME,
+    RS_COLLECTOR_COLLECTOR_DURATION_METRICNAME, RS_COLLECTOR_COLLECTOR_SAMPLES_METRICNAME,
+    RS_COLLECTOR_COLLECTOR_ERRORS_METRICNAME, RS_COLLECTOR_COLLECTOR_LAST_SUCCESS_METRICNAME,
+    RS_COLLECTOR_COLLECTOR_INIT_FAILURES_METRICNAME, RS_COLLECTOR_COLLECTOR_SANITIZED_SAMPLES_METRICNAME,
+    RS_COLLECTOR_COLLECTOR_REJECTED_SAMPLES_METRICNAME, RS_COLLECTOR_COLLECTOR_REJECTED_METADATA_METRICNAME};
+use crate::bosun::{Metadata, Sample, Tags};
+use crate::emitters::Emitter;
+use crate::filter::{self, MetricFilter};
+use crate::sanitizer::{self, Checked, Sanitizer};
+use crate::utils;
 
-pub fn run(collectors: Vec<Box<dyn Collector + Send>>, config: &Config) -> () {
-    let signal = chan_signal::notify(&[Signal::INT, Signal::TERM]);
+pub fn run(collectors: Vec<Box<dyn Collector + Send>>,
+           emitters: Vec<Box<dyn Emitter + Send>>,
+           config: Config,
+           config_path: Option<PathBuf>)
+           -> () {
+    let signal = chan_signal::notify(&[Signal::INT, Signal::TERM, Signal::HUP]);
     let timer = chan::tick(Duration::from_secs(TICK_INTERVAL_SEC));
     info!("Scheduler thread started.");
 
     let (to_main_tx, from_runners_rx) = chan::r#async();
-    let controllers = create_controllers(collectors, to_main_tx);
+    let mut controllers = create_controllers(collectors, to_main_tx.clone(), &config);
     info!("Loaded {} collectors: {:#?}", controllers.len(), controllers);
 
-    let (to_bosun_tx, from_main_rx) = chan::r#async();
-    let bosun_thread = if config.DontSend.or(Some(false)).unwrap() {
-        None
-    } else {
-        let bosun = Bosun::new(&config.Host, &config.Hostname, &config.Tags, from_main_rx);
-        Some(bosun.spawn())
-    };
+    let emitter_controllers = create_emitter_controllers(emitters);
+    info!("Loaded {} emitters: {:#?}", emitter_controllers.len(), emitter_controllers);
+    if emitter_controllers.is_empty() && !config.DontSend.unwrap_or(false) {
+        warn!("No emitters configured; samples will be discarded.");
+    }
 
-    event_loop(&controllers,
+    let mut config = Arc::new(config);
+    event_loop(&mut controllers,
                &signal,
                &timer,
                &from_runners_rx,
-               &to_bosun_tx);
+               &to_main_tx,
+               &emitter_controllers,
+               &mut config,
+               config_path.as_deref());
 
     // TODO: Generalize tear_down for all threads / JoinHandles
     tear_down(controllers);
-    to_bosun_tx.send(BosunRequest::Shutdown);
-    if let Some(thread) = bosun_thread {
-        let _ = thread.join();
-    }
+    tear_down_emitters(emitter_controllers);
 
     info!("Scheduler thread finished.");
 }
 
+/// Threads calling collectors are named by this prefix and the collector's id. A panic in such a thread is caught and
+/// reported as collection error instead of aborting rs-collector.
+pub static COLLECTOR_THREAD_NAME_PREFIX: &'static str = "collector:";
 static TICK_INTERVAL_SEC: u64 = 15u64;
+pub static DEFAULT_COLLECTOR_INTERVAL_SEC: u64 = 15u64;
+pub static DEFAULT_COLLECTOR_TIMEOUT_SEC: u64 = 60u64;
+pub static DEFAULT_MAX_COLLECTOR_TIMEOUTS: u32 = 3u32;
+pub static DEFAULT_MAX_COLLECTOR_FAILURES: u32 = 10u32;
+pub static DEFAULT_COLLECTOR_PROBE_INTERVAL_SEC: u64 = 600u64;
+static REPLACED_COLLECTOR_SHUTDOWN_WAIT_SEC: u64 = 3600u64;
 
-#[derive(Debug)]
 enum CollectorRequest {
     Helo,
-    Init,
     Metadata,
-    Sample,
+    Replace(Box<dyn Collector + Send>),
+    Shutdown,
+}
+
+#[derive(Debug)]
+enum EmitterRequest {
+    Metadata(Metadata),
+    Sample(Sample),
+    Reload(Arc<Config>),
     Shutdown,
 }
 
@@ -66,7 +98,150 @@ enum CollectorResponse {
     Id(Id),
     Metadata(Metadata),
     Sample(Sample),
-    CollectionError(Error)
+    Collected(Collected),
+    InitFailed,
+    Hung,
+}
+
+/**
+* Outcome of a finished collection
+**/
+#[derive(Debug)]
+struct Collected {
+    duration: Duration,
+    samples: usize,
+    failed: bool,
+}
+
+/**
+* Health and timing statistics of a collector derived by the Main Thread from the responses of its CollectorRunner
+**/
+#[derive(Debug, Default)]
+struct CollectorStats {
+    duration: Option<Duration>,
+    samples: usize,
+    errors: u64,
+    last_success: Option<u64>,
+    init_failures: u64,
+    sanitized_samples: u64,
+    rejected_samples: u64,
+    rejected_metadata: u64,
+}
+
+impl CollectorStats {
+    fn on_collected(&mut self, collected: &Collected, now: u64) {
+        self.duration = Some(collected.duration);
+        if collected.failed {
+            self.errors += 1;
+        } else {
+            self.samples = collected.samples;
+            self.last_success = Some(now);
+        }
+    }
+
+    fn on_init_failed(&mut self) {
+        self.init_failures += 1;
+    }
+
+    fn on_checked_sample(&mut self, checked: &Checked<Sample>) {
+        match *checked {
+            Checked::Valid(_) => {}
+            Checked::Sanitized(_) => self.sanitized_samples += 1,
+            Checked::Rejected => self.rejected_samples += 1,
+        }
+    }
+
+    fn on_rejected_metadata(&mut self) {
+        self.rejected_metadata += 1;
+    }
+
+    fn samples(&self, id: &Id) -> Vec<Sample> {
+        let mut tags = Tags::new();
+        tags.insert("collector".to_string(), sanitizer::sanitize(id));
+
+        let mut samples = vec![
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_SAMPLES_METRICNAME, self.samples as f64, tags.clone()),
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_ERRORS_METRICNAME, self.errors as f64, tags.clone()),
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_INIT_FAILURES_METRICNAME, self.init_failures as f64, tags.clone()),
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_SANITIZED_SAMPLES_METRICNAME, self.sanitized_samples as f64, tags.clone()),
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_REJECTED_SAMPLES_METRICNAME, self.rejected_samples as f64, tags.clone()),
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_REJECTED_METADATA_METRICNAME, self.rejected_metadata as f64, tags.clone()),
+        ];
+        if let Some(duration) = self.duration {
+            samples.push(Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_DURATION_METRICNAME, duration.as_millis() as f64, tags.clone()));
+        }
+        if let Some(last_success) = self.last_success {
+            samples.push(Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_LAST_SUCCESS_METRICNAME, last_success as f64, tags));
+        }
+
+        samples
+    }
+}
+
+/**
+* Computes the times at which a collector collects
+**/
+#[derive(Debug, Clone, Copy)]
+struct Schedule {
+    interval: Duration,
+    // Collect at wall-clock multiples of the interval, e.g., at :00, :15, :30, and :45 for 15 sec.
+    align: bool,
+    // Random delay within the splay; chosen once, so the collector keeps its interval.
+    offset: Duration,
+}
+
+impl Schedule {
+    fn new(interval: Duration, align: bool, splay: Duration) -> Schedule {
+        let max_offset = splay.min(interval).as_millis() as u64;
+        let offset = if max_offset > 0 { rand::thread_rng().gen_range(0, max_offset) } else { 0 };
+
+        Schedule { interval, align, offset: Duration::from_millis(offset) }
+    }
+
+    fn first(&self, now: SystemTime) -> SystemTime {
+        if self.align {
+            self.next_boundary(now)
+        } else {
+            now + self.offset
+        }
+    }
+
+    /// Returns the collection after the one scheduled at `previous`; a late collection does not make the following
+    /// collections pile up.
+    fn next(&self, previous: SystemTime, now: SystemTime) -> SystemTime {
+        if self.align {
+            self.next_boundary(now)
+        } else {
+            (previous + self.interval).max(now)
+        }
+    }
+
+    fn next_boundary(&self, now: SystemTime) -> SystemTime {
+        let interval = self.interval.as_millis();
+        let offset = self.offset.as_millis();
+        let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let boundary = (since_epoch.saturating_sub(offset) / interval + 1) * interval + offset;
+
+        UNIX_EPOCH + Duration::from_millis(boundary as u64)
+    }
+}
+
+/// Wakeups of a CollectorRunner
+enum Wakeup {
+    Due,
+    Timeout,
+    Request(Option<CollectorRequest>),
+}
+
+/**
+* State of the running or last collection, shared between CollectorRunner and its sample thread
+**/
+#[derive(Default)]
+struct Collection {
+    // Start of the running collection; reset by the sample thread when the collection finishes.
+    since: Option<Instant>,
+    // Result of the last finished collection until the CollectorRunner takes it.
+    result: Option<Result<(), String>>,
 }
 
 /**
@@ -76,6 +251,8 @@ struct CollectorController {
     id: Id,
     runner_tx: Sender<CollectorRequest>,
     runner_thread: Option<JoinHandle<()>>,
+    tags: Option<Tags>,
+    filter: Option<MetricFilter>,
 }
 
 impl Debug for CollectorController {
@@ -85,11 +262,13 @@ impl Debug for CollectorController {
 }
 
 impl CollectorController {
-    fn new(id: Id, runner_tx: Sender<CollectorRequest>) -> CollectorController {
+    fn new(id: Id, runner_tx: Sender<CollectorRequest>, tags: Option<Tags>, filter: Option<MetricFilter>) -> CollectorController {
         CollectorController {
             id: id,
             runner_tx: runner_tx,
             runner_thread: None,
+            tags: tags,
+            filter: filter,
         }
     }
 }
@@ -102,75 +281,91 @@ struct CollectorRunner {
     runner_rx: Receiver<CollectorRequest>,
     controller_tx: Sender<Msg<CollectorResponse>>,
     collector: Arc<Mutex<Box<dyn Collector + Send>>>,
-    tick_counter: i32,
-    tick_interval: i32,
+    schedule: Schedule,
+    next_collection: SystemTime,
+    timeout: Duration,
+    max_timeouts: u32,
+    collection: Arc<Mutex<Collection>>,
+    consecutive_timeouts: u32,
+    timeouts: u64,
+    health: CollectorHealth,
+    // Set for new collectors, which are initialized in the sample thread before their first collection.
+    init_pending: bool,
+    // Fires when the running collection exceeds its timeout.
+    timeout_timer: Option<Receiver<()>>,
 }
 
 impl CollectorRunner {
-    fn new(id: Id,
-           runner_rx: Receiver<CollectorRequest>,
+    fn new(runner_rx: Receiver<CollectorRequest>,
            controller_tx: Sender<Msg<CollectorResponse>>,
-           collector: Box<dyn Collector + Send>)
+           collector: Box<dyn Collector + Send>,
+           schedule: Schedule,
+           timeout: Duration,
+           max_timeouts: u32,
+           health: CollectorHealth)
            -> CollectorRunner {
 
-        let tick_interval = get_tick_interval(&collector);
-
         CollectorRunner {
-            id: id,
+            id: collector.id().clone(),
             runner_rx: runner_rx,
             controller_tx: controller_tx,
             collector: Arc::new(Mutex::new(collector)),
-            tick_counter: 0, // Make sure, first tick initiates a sample
-            tick_interval,
+            schedule,
+            next_collection: schedule.first(SystemTime::now()),
+            timeout,
+            max_timeouts,
+            collection: Arc::new(Mutex::new(Collection::default())),
+            consecutive_timeouts: 0,
+            timeouts: 0,
+            health,
+            init_pending: true,
+            timeout_timer: None,
         }
     }
 
     fn spawn(mut self) -> JoinHandle<()> {
         thread::spawn(move || {
             info!("CollectorRunner {} thread started.", self.id);
+            let mut timer = chan::after(delay_until(self.next_collection));
             loop {
-                let message = self.runner_rx.recv();
+                let message = match self.wait(&timer) {
+                    Wakeup::Due => {
+                        self.on_due();
+                        timer = chan::after(delay_until(self.next_collection));
+                        continue;
+                    }
+                    Wakeup::Timeout => {
+                        self.on_timeout();
+                        continue;
+                    }
+                    Wakeup::Request(message) => message,
+                };
                 match message {
                     Some(CollectorRequest::Helo) => {
                         debug!("CollectorRunner {} received 'Helo' message.", &self.id);
                         self.controller_tx.send(
                             Msg::Collector(self.id.clone(),CollectorResponse::Id(self.id.clone())));
                     },
-                    Some(CollectorRequest::Init) => {
-                        // TODO: Add failure management exp backoff timer to wait before reconnecting.
-                        let exp_backoff = 60;
-                        debug!("CollectorRunner {} received 'Init' message. Waiting {} sec.", &self.id, exp_backoff);
-                        thread::sleep(Duration::from_secs(exp_backoff));
-                        let collector = self.collector.clone();
-                        let mut collector = collector.lock().unwrap();
-                        match collector.init() {
-                            Ok(_) => {
-                                info!("CollectorRunner {} successfully re-initialized collector.", &self.id);
-                            },
-                            Err(_) => {
-                                error!("CollectorRunner {} failed to re-initialize collector.", &self.id);
-                            }
-                        }
-                    },
                     Some(CollectorRequest::Metadata) => {
                         debug!("CollectorRunner {} received 'Metadata' message.", &self.id);
                         self.collect_metadata();
                     },
-                    Some(CollectorRequest::Sample) => {
-                        if self.tick_counter % self.tick_interval == 0 {
-                            debug!("CollectorRunner {} received 'Sample' message. Sampling.", &self.id);
-                            self.collect_sample();
-                            self.tick_counter = 1;
-                        } else {
-                            debug!("CollectorRunner {} received 'Sample' message. Skipping.", &self.id);
-                            self.tick_counter = self.tick_counter + 1;
-                        }
+                    Some(CollectorRequest::Replace(collector)) => {
+                        debug!("CollectorRunner {} received 'Replace' message.", &self.id);
+                        self.replace_collector(collector);
                     },
                     Some(CollectorRequest::Shutdown) => {
                         debug!("CollectorRunner {} received 'Shutdown' message.", &self.id);
-                        let collector = self.collector.clone();
-                        let mut collector = collector.lock().unwrap();
-                        collector.shutdown();
+                        // A hung collector cannot be shut down; don't let it block the shutdown of rs-collector.
+                        if self.is_timed_out() {
+                            warn!("CollectorRunner {} cannot shut down collector, because its collection timed out.", &self.id);
+                        } else {
+                            let collector = self.collector.clone();
+                            let shutdown_thread = spawn_collector_thread(&self.id, move || lock(&collector).shutdown());
+                            if shutdown_thread.join().is_err() {
+                                error!("CollectorRunner {} collector panicked during shutdown.", &self.id);
+                            }
+                        }
                         self.controller_tx.send(
                             Msg::Collector(self.id.clone(),CollectorResponse::Id(self.id.clone())));
                         break;
@@ -184,17 +379,74 @@ impl CollectorRunner {
         })
     }
 
+    /// Waits until the next collection is due, the running collection exceeds its timeout, or a request arrives.
+    fn wait(&self, timer: &Receiver<()>) -> Wakeup {
+        let runner_rx = &self.runner_rx;
+        let mut wakeup = Wakeup::Due;
+        match self.timeout_timer {
+            Some(ref timeout_timer) => chan_select! {
+                timer.recv() => {},
+                timeout_timer.recv() => {
+                    wakeup = Wakeup::Timeout;
+                },
+                runner_rx.recv() -> message => {
+                    wakeup = Wakeup::Request(message);
+                },
+            },
+            None => chan_select! {
+                timer.recv() => {},
+                runner_rx.recv() -> message => {
+                    wakeup = Wakeup::Request(message);
+                },
+            },
+        }
+
+        wakeup
+    }
+
+    /// Counts a timeout as soon as the running collection exceeds it, instead of when the next collection is due, and
+    /// checks again after another timeout as long as the collection keeps running.
+    fn on_timeout(&mut self) {
+        self.timeout_timer = None;
+        let since = match self.collection.lock().unwrap().since {
+            Some(since) => since,
+            None => return,
+        };
+        self.check_timeout();
+        let remaining = (since + self.timeout).saturating_duration_since(Instant::now());
+        let delay = if remaining > Duration::from_secs(0) { remaining } else { self.timeout };
+        self.timeout_timer = Some(chan::after(delay));
+    }
+
+    /// Collects unless the collector is backing off, and schedules the next collection.
+    fn on_due(&mut self) {
+        self.update_health();
+        let now = SystemTime::now();
+        match self.health.retry_at() {
+            Some(retry_at) if !self.health.allows_collection(Instant::now()) => {
+                debug!("CollectorRunner {} is due. Backing off; collector is {}.", &self.id, self.health.state());
+                self.next_collection = now + retry_at.saturating_duration_since(Instant::now());
+            }
+            _ => {
+                debug!("CollectorRunner {} is due. Sampling.", &self.id);
+                self.collect_sample();
+                self.next_collection = self.schedule.next(self.next_collection, now);
+            }
+        }
+        self.send_stats();
+    }
+
     fn collect_metadata(&mut self) {
         let collector = self.collector.clone();
-        let lock = collector.try_lock();
-        match lock {
-            Ok(_) => {
+        let guard = try_lock(&collector);
+        match guard {
+            Some(_) => {
                 let id = self.id.clone();
                 let tx = self.controller_tx.clone();
                 let collector = self.collector.clone();
-                thread::spawn(move || {
+                spawn_collector_thread(&self.id, move || {
                     debug!("CollectorRunner {} spawned metadata thread.", &id);
-                    let ref collector = *collector.lock().unwrap();
+                    let ref collector = *lock(&collector);
                     let metadata = collector.metadata();
                     for m in metadata.into_iter() {
                         tx.send(
@@ -203,7 +455,7 @@ impl CollectorRunner {
                     debug!("CollectorRunner {} finished metadata thread.", &id);
                 });
             }
-            Err(_) => {
+            None => {
                 trace!("CollectorRunner {} metadata already running ...", &self.id);
             }
         }
@@ -211,68 +463,307 @@ impl CollectorRunner {
 
     fn collect_sample(&mut self) {
         let collector = self.collector.clone();
-        let lock = collector.try_lock();
-        match lock {
-            Ok(_) => {
+        let guard = try_lock(&collector);
+        match guard {
+            Some(_) => {
+                self.consecutive_timeouts = 0;
+                self.collection.lock().unwrap().since = Some(Instant::now());
                 let id = self.id.clone();
                 let tx = self.controller_tx.clone();
                 let collector = self.collector.clone();
-                thread::spawn(move || {
+                let collection = self.collection.clone();
+                let timeout = self.timeout;
+                let needs_init = self.init_pending || self.health.needs_init();
+                self.init_pending = false;
+                self.timeout_timer = Some(chan::after(timeout));
+                spawn_collector_thread(&self.id, move || {
                     debug!("CollectorRunner {} spawned sample thread.", &id);
-                    let ref collector = *collector.lock().unwrap();
-                    match collector.collect() {
+                    let mut collector = lock(&collector);
+                    let started = Instant::now();
+                    // A failed collector is re-initialized before each attempt, e.g., to reconnect to its database.
+                    let init_result = if needs_init {
+                        catch_panic(|| collector.init().map_err(|err| *err))
+                    } else {
+                        Ok(())
+                    };
+                    if let Err(ref err) = init_result {
+                        debug!("CollectorRunner {} failed to initialize collector: {}", &id, err);
+                        tx.send(Msg::Collector(id.clone(), CollectorResponse::InitFailed));
+                    }
+                    let result = init_result.and_then(|_| catch_panic(|| collector.collect()));
+                    let elapsed = started.elapsed();
+                    let collected = Collected {
+                        duration: elapsed,
+                        samples: result.as_ref().map(|samples| samples.len()).unwrap_or(0),
+                        failed: result.is_err(),
+                    };
+                    if elapsed > timeout {
+                        warn!("CollectorRunner {} finished collection after {} sec, exceeding its timeout of {} sec.",
+                              &id, elapsed.as_secs(), timeout.as_secs());
+                    }
+                    {
+                        let mut collection = collection.lock().unwrap();
+                        collection.since = None;
+                        collection.result = Some(result.as_ref().map(|_| ()).map_err(|err| err.to_string()));
+                    }
+                    match result {
                         Ok(samples) => {
+                            for m in collector.pending_metadata().into_iter() {
+                                tx.send(
+                                    Msg::Collector(id.clone(), CollectorResponse::Metadata(m)));
+                            }
                             for s in samples.into_iter() {
                                 tx.send(
                                     Msg::Collector(id.clone(), CollectorResponse::Sample(s)));
                             }
                         },
                         Err(error) => {
-                            warn!("CollectorResponse {} received collection error {}", &id, error);
-                            tx.send(
-                                Msg::Collector(id.clone(), CollectorResponse::CollectionError(error)));
+                            debug!("CollectorRunner {} received collection error {}", &id, error);
                         }
-
                     }
+                    tx.send(Msg::Collector(id.clone(), CollectorResponse::Collected(collected)));
                     debug!("CollectorRunner {} finished sample thread.", &id);
                 });
             }
-            Err(_) => {
+            None => {
                 trace!("CollectorRunner {} sampling already running ...", &self.id);
             }
         }
     }
+
+    /// Passes the result of the last finished collection to the collector's failure state machine.
+    fn update_health(&mut self) {
+        let result = self.collection.lock().unwrap().result.take();
+        match result {
+            Some(Ok(())) => self.health.on_success(),
+            Some(Err(reason)) => self.health.on_failure(Instant::now(), &reason),
+            None => {}
+        }
+    }
+
+    /// Counts a timeout if the running collection exceeds the timeout and requests a new collector instance after too
+    /// many consecutive timeouts, because a hung collection keeps the collector locked forever.
+    fn check_timeout(&mut self) {
+        if !self.is_timed_out() {
+            return;
+        }
+        let elapsed = self.collection.lock().unwrap().since.map(|since| since.elapsed()).unwrap_or_default();
+
+        self.consecutive_timeouts += 1;
+        self.timeouts += 1;
+        error!("CollectorRunner {} collection has been running for {} sec, exceeding its timeout of {} sec ({}/{}).",
+               &self.id, elapsed.as_secs(), self.timeout.as_secs(), self.consecutive_timeouts, self.max_timeouts);
+
+        if self.consecutive_timeouts >= self.max_timeouts {
+            error!("CollectorRunner {} collector is hung; requesting a new instance.", &self.id);
+            self.consecutive_timeouts = 0;
+            self.controller_tx.send(Msg::Collector(self.id.clone(), CollectorResponse::Hung));
+        }
+    }
+
+    fn is_timed_out(&self) -> bool {
+        match self.collection.lock().unwrap().since {
+            Some(since) => since.elapsed() > self.timeout,
+            None => false,
+        }
+    }
+
+    fn send_stats(&self) {
+        let mut tags = Tags::new();
+        tags.insert("collector".to_string(), sanitizer::sanitize(&self.id));
+        let samples = vec![
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_TIMEOUTS_METRICNAME, self.timeouts as f64, tags.clone()),
+            Sample::new_with_tags(RS_COLLECTOR_COLLECTOR_STATE_METRICNAME, self.health.state().value(), tags),
+        ];
+        for s in samples.into_iter() {
+            self.controller_tx.send(Msg::Collector(self.id.clone(), CollectorResponse::Sample(s)));
+        }
+    }
+
+    /// Replaces a hung collector by a new instance, which is initialized before its first collection. The hung
+    /// instance is shut down as soon as its collection returns.
+    fn replace_collector(&mut self, collector: Box<dyn Collector + Send>) {
+        let hung = ::std::mem::replace(&mut self.collector, Arc::new(Mutex::new(collector)));
+        let id = self.id.clone();
+        // Threads cannot be killed, so the thread of a collection that never returns leaks; at least the thread waiting
+        // to shut down the hung instance gives up eventually.
+        spawn_collector_thread(&self.id, move || {
+            let deadline = Instant::now() + Duration::from_secs(REPLACED_COLLECTOR_SHUTDOWN_WAIT_SEC);
+            loop {
+                let mut collector = match hung.try_lock() {
+                    Ok(collector) => collector,
+                    Err(TryLockError::Poisoned(err)) => err.into_inner(),
+                    Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                        thread::sleep(Duration::from_secs(1));
+                        continue;
+                    }
+                    Err(TryLockError::WouldBlock) => {
+                        warn!("CollectorRunner {} gave up shutting down replaced collector, because its collection did not return within {} sec.",
+                              &id, REPLACED_COLLECTOR_SHUTDOWN_WAIT_SEC);
+                        return;
+                    }
+                };
+                collector.shutdown();
+                info!("CollectorRunner {} shut down replaced collector.", &id);
+                return;
+            }
+        });
+
+        self.collection = Arc::new(Mutex::new(Collection::default()));
+        self.consecutive_timeouts = 0;
+        self.init_pending = true;
+        self.timeout_timer = None;
+    }
+}
+
+fn spawn_collector_thread<F: FnOnce() + Send + 'static>(id: &Id, f: F) -> JoinHandle<()> {
+    thread::Builder::new()
+        .name(format!("{}{}", COLLECTOR_THREAD_NAME_PREFIX, id))
+        .spawn(f)
+        .expect("Failed to spawn collector thread")
 }
 
-fn get_tick_interval(collector: &Box<dyn Collector + Send>) -> i32 {
-    collector.get_tick_interval()
+/// Locks a collector even if a previous call panicked while holding the lock; the collector is re-initialized anyway.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns `None` if the collector is locked by a running call.
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<T>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(err)) => Some(err.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Turns a panic of a collector into a collection error, so the collector goes through the normal failure handling.
+fn catch_panic<T, F: FnOnce() -> Result<T, Error>>(f: F) -> Result<T, Error> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => Err(Error::CollectionError(format!("collector panicked: {}", panic_message(&*payload)))),
+    }
+}
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown reason".to_string()
+    }
+}
+
+/**
+* Handle for Main Thread to communicate with EmitterRunner
+**/
+struct EmitterController {
+    id: Id,
+    runner_tx: Sender<EmitterRequest>,
+    runner_thread: Option<JoinHandle<()>>,
+}
+
+impl Debug for EmitterController {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "EmitterController {{ id: {:#?} }}", self.id)
+    }
+}
+
+/**
+* Passes samples and metadata to the emitter <T> and periodically flushes it
+**/
+struct EmitterRunner {
+    id: Id,
+    runner_rx: Receiver<EmitterRequest>,
+    emitter: Box<dyn Emitter + Send>,
+}
+
+impl EmitterRunner {
+    fn spawn(mut self) -> JoinHandle<()> {
+        let timer = chan::tick(Duration::from_secs(self.emitter.get_tick_interval()));
+
+        thread::spawn(move || {
+            info!("EmitterRunner {} thread started.", self.id);
+            let runner_rx = &self.runner_rx;
+            loop {
+                chan_select! {
+                    timer.recv() => {
+                        debug!("EmitterRunner {} has been ticked. Flushing.", &self.id);
+                        self.emitter.flush();
+                    },
+                    runner_rx.recv() -> message => {
+                        match message {
+                            Some(EmitterRequest::Metadata(metadata)) => {
+                                debug!("EmitterRunner {} received metadata '{}'.", &self.id, &metadata.metric);
+                                self.emitter.emit_metadata(&metadata);
+                            },
+                            Some(EmitterRequest::Sample(sample)) => {
+                                trace!("EmitterRunner {} received sample '{}'.", &self.id, sample.time);
+                                self.emitter.emit_sample(sample);
+                            },
+                            Some(EmitterRequest::Reload(config)) => {
+                                debug!("EmitterRunner {} received 'Reload' message.", &self.id);
+                                self.emitter.reload(&config);
+                            },
+                            Some(EmitterRequest::Shutdown) | None => {
+                                debug!("EmitterRunner {} received 'Shutdown' message.", &self.id);
+                                self.emitter.shutdown();
+                                break;
+                            },
+                        }
+                    }
+                }
+            }
+            info!("EmitterRunner {} thread finished.", self.id);
+        })
+    }
+}
+
+fn delay_until(time: SystemTime) -> Duration {
+    time.duration_since(SystemTime::now()).unwrap_or_default()
 }
 
 fn create_controllers(
     collectors: Vec<Box<dyn Collector + Send>>,
-    runners_to_main_tx: Sender<Msg<CollectorResponse>>)
+    runners_to_main_tx: Sender<Msg<CollectorResponse>>,
+    config: &Config)
     -> HashMap<String, CollectorController> {
 
     let mut controllers: HashMap<String, CollectorController> = HashMap::new();
+    let default_timeout = config.CollectorTimeout.unwrap_or(DEFAULT_COLLECTOR_TIMEOUT_SEC);
+    let max_timeouts = config.MaxCollectorTimeouts.unwrap_or(DEFAULT_MAX_COLLECTOR_TIMEOUTS);
+    let max_failures = config.MaxCollectorFailures.unwrap_or(DEFAULT_MAX_COLLECTOR_FAILURES);
+    let probe_interval = Duration::from_secs(config.CollectorProbeInterval.unwrap_or(DEFAULT_COLLECTOR_PROBE_INTERVAL_SEC));
+    let default_interval = collectors::parse_interval(&config.CollectorInterval)
+        .unwrap_or_else(|err| {
+            error!("Ignoring CollectorInterval, because {}", err);
+            None
+        })
+        .unwrap_or_else(|| Duration::from_secs(DEFAULT_COLLECTOR_INTERVAL_SEC));
+    let align = config.AlignCollections.unwrap_or(false);
+    let splay = match config.CollectorSplay {
+        Some(ref splay) => utils::parse_duration(splay).unwrap_or_else(|err| {
+            error!("Ignoring CollectorSplay, because {}", err);
+            Duration::from_secs(0)
+        }),
+        None => Duration::from_secs(0),
+    };
 
-    for mut c in collectors.into_iter() {
-        // Initialization might be moved to collector threads?
-        match c.init() {
-            Ok(_) => {
-                info!("Initialized collector {}", c.id());
-            }
-            Err(err) => {
-                error!("Failed to initialize collector {}: {:?}. Adding anyway.", c.id(), err);
-            }
-        }
-
+    // Collectors are initialized by their runners before their first collection, so a panic is isolated.
+    for c in collectors.into_iter() {
         let (to_runner_tx, from_controller_rx) = chan::r#async();
         let id = c.id().clone();
-        let mut controller = CollectorController::new(id.clone(), to_runner_tx);
-        let runner = CollectorRunner::new(id.clone(),
-                                          from_controller_rx,
+        let timeout = Duration::from_secs(c.get_timeout().unwrap_or(default_timeout));
+        let schedule = Schedule::new(c.get_interval().unwrap_or(default_interval), align, splay);
+        let mut controller = CollectorController::new(id.clone(), to_runner_tx, c.get_tags().cloned(), c.get_filter().cloned());
+        let runner = CollectorRunner::new(from_controller_rx,
                                           runners_to_main_tx.clone(),
-                                          c);
+                                          c,
+                                          schedule,
+                                          timeout,
+                                          max_timeouts,
+                                          CollectorHealth::new(id.clone(), max_failures, probe_interval));
         let runner_thread = runner.spawn();
 
         controller.runner_thread = Some(runner_thread);
@@ -281,11 +772,37 @@ fn create_controllers(
     controllers
 }
 
-fn event_loop(threads: &HashMap<String, CollectorController>,
+fn create_emitter_controllers(emitters: Vec<Box<dyn Emitter + Send>>) -> Vec<EmitterController> {
+    let mut controllers = Vec::new();
+
+    for e in emitters.into_iter() {
+        let (to_runner_tx, from_controller_rx) = chan::r#async();
+        let id = e.id().clone();
+        let runner = EmitterRunner {
+            id: id.clone(),
+            runner_rx: from_controller_rx,
+            emitter: e,
+        };
+        let runner_thread = runner.spawn();
+
+        controllers.push(EmitterController {
+            id: id,
+            runner_tx: to_runner_tx,
+            runner_thread: Some(runner_thread),
+        });
+    }
+
+    controllers
+}
+
+fn event_loop(threads: &mut HashMap<String, CollectorController>,
               signal_rx: &Receiver<Signal>,
               timer: &Receiver<Sender<()>>,
               collectors_rx: &Receiver<Msg<CollectorResponse>>,
-              bosun_tx: &Sender<BosunRequest>)
+              to_main_tx: &Sender<Msg<CollectorResponse>>,
+              emitters: &[EmitterController],
+              config: &mut Arc<Config>,
+              config_path: Option<&Path>)
               -> () {
     info!("Scheduler thread entering event loop.");
 
@@ -295,16 +812,36 @@ fn event_loop(threads: &HashMap<String, CollectorController>,
         cc.runner_tx.send(CollectorRequest::Metadata)
     }
 
+    let mut stats: HashMap<Id, CollectorStats> = threads.keys().map(|id| (id.clone(), CollectorStats::default())).collect();
+    let mut global_filter = create_global_filter(config);
+    let mut sanitizer = Sanitizer::new(config.InvalidNames.unwrap_or_default());
+
     loop {
         debug!("Scheduler thread event loop.");
         chan_select! {
-            signal_rx.recv() => {
-                break
+            signal_rx.recv() -> signal => {
+                match signal {
+                    Some(Signal::HUP) => {
+                        info!("Scheduler received SIGHUP; reloading configuration.");
+                        reload(threads, emitters, config, config_path, to_main_tx, &mut stats);
+                        global_filter = create_global_filter(config);
+                        sanitizer.set_policy(config.InvalidNames.unwrap_or_default());
+                    },
+                    _ => break,
+                }
             },
             timer.recv() => {
+                // Collectors run on their own schedules; the timer only paces their statistics.
                 trace!("Scheduler: I've been ticked.");
-                for cc in threads.values() {
-                    cc.runner_tx.send(CollectorRequest::Sample)
+                for (id, s) in &stats {
+                    let samples = s.samples(id).into_iter()
+                        .filter_map(|s| filter::filter_sample(s, &[global_filter.as_ref()]))
+                        .filter_map(|s| sanitizer.sample(id, s).into_option());
+                    for sample in samples {
+                        for ec in emitters {
+                            ec.runner_tx.send(EmitterRequest::Sample(sample.clone()));
+                        }
+                    }
                 }
             },
             collectors_rx.recv() -> message => {
@@ -314,17 +851,49 @@ fn event_loop(threads: &HashMap<String, CollectorController>,
                     }
                     Some(Msg::Collector(id, CollectorResponse::Metadata(metadata))) => {
                         debug!("Scheduler received metadata from '{}' for '{}'.", &id, &metadata.metric );
-                        bosun_tx.send(BosunRequest::Metadata(metadata));
+                        let filters = [threads.get(&id).and_then(|cc| cc.filter.as_ref()), global_filter.as_ref()];
+                        let checked = filter::filter_metadata(metadata, &filters).map(|metadata| sanitizer.metadata(&id, metadata));
+                        if let Some(Checked::Rejected) = checked {
+                            stats.entry(id.clone()).or_insert_with(CollectorStats::default).on_rejected_metadata();
+                        }
+                        if let Some(metadata) = checked.and_then(Checked::into_option) {
+                            for ec in emitters {
+                                ec.runner_tx.send(EmitterRequest::Metadata(metadata.clone()));
+                            }
+                        }
                     }
-                    Some(Msg::Collector(id, CollectorResponse::Sample(sample))) => {
+                    Some(Msg::Collector(id, CollectorResponse::Sample(mut sample))) => {
                         debug!("Scheduler received sample from '{}' for '{}'.", &id, &sample.time );
-                        bosun_tx.send(BosunRequest::Sample(sample));
+                        if let Some(tags) = threads.get(&id).and_then(|cc| cc.tags.as_ref()) {
+                            sample.tags.extend(tags.clone());
+                        }
+                        let filters = [threads.get(&id).and_then(|cc| cc.filter.as_ref()), global_filter.as_ref()];
+                        if let Some(sample) = filter::filter_sample(sample, &filters) {
+                            let checked = sanitizer.sample(&id, sample);
+                            stats.entry(id.clone()).or_insert_with(CollectorStats::default).on_checked_sample(&checked);
+                            if let Some(sample) = checked.into_option() {
+                                for ec in emitters {
+                                    ec.runner_tx.send(EmitterRequest::Sample(sample.clone()));
+                                }
+                            }
+                        }
+                    },
+                    Some(Msg::Collector(id, CollectorResponse::Collected(collected))) => {
+                        debug!("Scheduler received collection of {} samples from '{}' in {} ms.",
+                               collected.samples, &id, collected.duration.as_millis());
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                        stats.entry(id).or_insert_with(CollectorStats::default).on_collected(&collected, now);
+                    },
+                    Some(Msg::Collector(id, CollectorResponse::InitFailed)) => {
+                        debug!("Scheduler received init failure from '{}'.", &id);
+                        stats.entry(id).or_insert_with(CollectorStats::default).on_init_failed();
                     },
-                    Some(Msg::Collector(id, CollectorResponse::CollectionError(error))) => {
-                        debug!("Scheduler received collection error from {} '{}'.", &id, &error);
-                        // TODO: Take care of failure case
-                        if let Some(cc) = threads.get(&id) {
-                            cc.runner_tx.send(CollectorRequest::Init);
+                    Some(Msg::Collector(id, CollectorResponse::Hung)) => {
+                        debug!("Scheduler received hung collector {}.", &id);
+                        match (threads.get(&id), collectors::create_collector(config, &id)) {
+                            (Some(cc), Ok(Some(collector))) => cc.runner_tx.send(CollectorRequest::Replace(collector)),
+                            (_, Err(err)) => error!("Failed to create new instance of hung collector {}, because {}.", &id, err),
+                            _ => error!("Failed to create new instance of hung collector {}.", &id),
                         }
                     },
                     None => {
@@ -337,6 +906,84 @@ fn event_loop(threads: &HashMap<String, CollectorController>,
     }
 }
 
+fn create_global_filter(config: &Config) -> Option<MetricFilter> {
+    config.Filter.as_ref().and_then(|filter| MetricFilter::new(filter, "Filter")
+        .map_err(|err| error!("Ignoring Filter, because {}", err))
+        .ok())
+}
+
+/// Loads the config file again and applies it: collectors that have been removed or changed are shut down, new and
+/// changed collectors are started, and emitters switch to the new host and tags. An invalid config is rejected and the
+/// current config keeps running.
+fn reload(threads: &mut HashMap<String, CollectorController>,
+          emitters: &[EmitterController],
+          config: &mut Arc<Config>,
+          config_path: Option<&Path>,
+          to_main_tx: &Sender<Msg<CollectorResponse>>,
+          stats: &mut HashMap<Id, CollectorStats>) {
+    let config_path = match config_path {
+        Some(config_path) => config_path,
+        None => {
+            warn!("Cannot reload configuration, because rs-collector has been started without config file.");
+            return;
+        }
+    };
+    let new_config = match Config::load_from_rs_collector_config(config_path) {
+        Ok(new_config) => new_config,
+        Err(err) => {
+            error!("Rejected configuration '{}', because {}. Keeping current configuration.", config_path.display(), err);
+            return;
+        }
+    };
+
+    let new_collectors = match collectors::create_collectors(&new_config) {
+        Ok(new_collectors) => new_collectors,
+        Err(err) => {
+            error!("Rejected configuration '{}', because {}. Keeping current configuration.", config_path.display(), err);
+            return;
+        }
+    };
+    let mut new_collectors: HashMap<Id, Box<dyn Collector + Send>> = new_collectors
+        .into_iter()
+        .map(|c| (c.id().clone(), c))
+        .collect();
+    let obsolete: Vec<Id> = threads.keys()
+        .filter(|id| !new_collectors.contains_key(*id) ||
+            collectors::collector_config(config, id) != collectors::collector_config(&new_config, id))
+        .cloned()
+        .collect();
+    let removed = obsolete.iter().filter(|id| !new_collectors.contains_key(*id)).count();
+
+    let mut stopped = HashMap::new();
+    for id in obsolete.iter() {
+        if let Some(cc) = threads.remove(id) {
+            stopped.insert(id.clone(), cc);
+        }
+        if !new_collectors.contains_key(id) {
+            stats.remove(id);
+        }
+    }
+    tear_down(stopped);
+
+    // Unchanged collectors keep running.
+    for id in threads.keys() {
+        new_collectors.remove(id);
+    }
+    let started = create_controllers(new_collectors.into_values().collect(), to_main_tx.clone(), &new_config);
+    for cc in started.values() {
+        cc.runner_tx.send(CollectorRequest::Metadata)
+    }
+    info!("Reloaded configuration '{}': removed {}, restarted {}, and added {} collectors.",
+          config_path.display(), removed, obsolete.len() - removed, started.len() - (obsolete.len() - removed));
+    threads.extend(started);
+
+    let new_config = Arc::new(new_config);
+    for ec in emitters {
+        ec.runner_tx.send(EmitterRequest::Reload(new_config.clone()));
+    }
+    *config = new_config;
+}
+
 fn tear_down(mut threads: HashMap<String, CollectorController>) -> () {
     info!("Scheduler thread shutting down ...");
     for cc in threads.values() {
@@ -349,3 +996,142 @@ fn tear_down(mut threads: HashMap<String, CollectorController>) -> () {
         let _ = jh.join();
     }
 }
+
+fn tear_down_emitters(emitters: Vec<EmitterController>) -> () {
+    info!("Scheduler thread shutting down emitters ...");
+    for ec in emitters.iter() {
+        ec.runner_tx.send(EmitterRequest::Shutdown)
+    }
+
+    info!("Scheduler thread waiting for emitter threads to finish ...");
+    for ec in emitters.into_iter() {
+        if let Some(jh) = ec.runner_thread {
+            let _ = jh.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dummy {
+        id: Id,
+    }
+
+    impl Collector for Dummy {
+        fn init(&mut self) -> Result<(), Box<Error>> { Ok(()) }
+        fn id(&self) -> &Id { &self.id }
+        fn metadata(&self) -> Vec<Metadata> { Vec::new() }
+        fn collect(&self) -> Result<Vec<Sample>, Error> { Ok(Vec::new()) }
+        fn shutdown(&mut self) {}
+    }
+
+    #[test]
+    fn request_new_instance_after_max_consecutive_timeouts() {
+        let (_, runner_rx) = chan::r#async();
+        let (controller_tx, controller_rx) = chan::r#async();
+        let id = "dummy".to_string();
+        let collector = Box::new(Dummy { id: id.clone() });
+        let health = CollectorHealth::new(id.clone(), 3, Duration::from_secs(60));
+        let schedule = Schedule::new(Duration::from_secs(15), false, Duration::from_secs(0));
+        let mut runner = CollectorRunner::new(runner_rx, controller_tx, collector, schedule, Duration::from_secs(1), 2, health);
+        runner.collection.lock().unwrap().since = Some(Instant::now() - Duration::from_secs(2));
+
+        runner.check_timeout();
+        assert_eq!(runner.consecutive_timeouts, 1);
+        runner.check_timeout();
+
+        assert_eq!(runner.timeouts, 2);
+        match controller_rx.recv() {
+            Some(Msg::Collector(_, CollectorResponse::Hung)) => {},
+            _ => panic!("Expected 'Hung' response."),
+        }
+    }
+
+    #[test]
+    fn count_timeout_before_next_collection_is_due() {
+        let (_runner_tx, runner_rx) = chan::r#async();
+        let (controller_tx, _controller_rx) = chan::r#async();
+        let id = "dummy".to_string();
+        let collector = Box::new(Dummy { id: id.clone() });
+        let health = CollectorHealth::new(id.clone(), 3, Duration::from_secs(60));
+        let schedule = Schedule::new(Duration::from_secs(300), false, Duration::from_secs(0));
+        let timeout = Duration::from_millis(50);
+        let mut runner = CollectorRunner::new(runner_rx, controller_tx, collector, schedule, timeout, 3, health);
+        runner.collection.lock().unwrap().since = Some(Instant::now());
+        runner.timeout_timer = Some(chan::after(timeout));
+
+        let next_collection = chan::after(Duration::from_secs(300));
+        match runner.wait(&next_collection) {
+            Wakeup::Timeout => runner.on_timeout(),
+            _ => panic!("Expected timeout."),
+        }
+        assert_eq!(runner.timeouts, 1);
+        assert!(runner.timeout_timer.is_some());
+
+        runner.collection.lock().unwrap().since = None;
+        match runner.wait(&next_collection) {
+            Wakeup::Timeout => runner.on_timeout(),
+            _ => panic!("Expected timeout."),
+        }
+        assert_eq!(runner.timeouts, 1);
+        assert!(runner.timeout_timer.is_none());
+    }
+
+    #[test]
+    fn aligned_schedule_collects_at_wall_clock_boundaries() {
+        let schedule = Schedule { interval: Duration::from_secs(60), align: true, offset: Duration::from_secs(5) };
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(schedule.first(at(1_000_000_000)), at(1_000_000_025));
+        assert_eq!(schedule.next(at(1_000_000_025), at(1_000_000_025)), at(1_000_000_085));
+        assert_eq!(schedule.next(at(1_000_000_025), at(1_000_000_100)), at(1_000_000_145));
+    }
+
+    #[test]
+    fn unaligned_schedule_keeps_interval() {
+        let schedule = Schedule { interval: Duration::from_secs(30), align: false, offset: Duration::from_secs(3) };
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(schedule.first(at(100)), at(103));
+        assert_eq!(schedule.next(at(103), at(103)), at(133));
+        assert_eq!(schedule.next(at(103), at(200)), at(200));
+    }
+
+    #[test]
+    fn collector_stats_keep_last_success() {
+        let mut stats = CollectorStats::default();
+        stats.on_collected(&Collected { duration: Duration::from_millis(1500), samples: 7, failed: false }, 100);
+        stats.on_collected(&Collected { duration: Duration::from_millis(200), samples: 0, failed: true }, 115);
+        stats.on_init_failed();
+        stats.on_checked_sample(&Checked::Sanitized(Sample::new("galera.wsrep.local_state", 4f64)));
+        stats.on_checked_sample(&Checked::Valid(Sample::new("galera.wsrep.local_state", 4f64)));
+        stats.on_checked_sample(&Checked::Rejected);
+        stats.on_checked_sample(&Checked::Rejected);
+        stats.on_rejected_metadata();
+
+        let samples = stats.samples(&"galera#root@localhost".to_string());
+        let value = |metric: &str| samples.iter().find(|s| s.metric == metric).map(|s| s.value);
+
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_SAMPLES_METRICNAME), Some(7f64));
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_ERRORS_METRICNAME), Some(1f64));
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_INIT_FAILURES_METRICNAME), Some(1f64));
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_DURATION_METRICNAME), Some(200f64));
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_LAST_SUCCESS_METRICNAME), Some(100f64));
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_SANITIZED_SAMPLES_METRICNAME), Some(1f64));
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_REJECTED_SAMPLES_METRICNAME), Some(2f64));
+        assert_eq!(value(RS_COLLECTOR_COLLECTOR_REJECTED_METADATA_METRICNAME), Some(1f64));
+        assert!(samples.iter().all(|s| s.tags["collector"] == "galera_root_localhost"));
+    }
+
+    #[test]
+    fn catch_panic_as_collection_error() {
+        let result: Result<(), Error> = catch_panic(|| panic!("index out of bounds"));
+
+        match result {
+            Err(Error::CollectionError(msg)) => assert_eq!(msg, "collector panicked: index out of bounds"),
+            _ => panic!("Expected collection error."),
+        }
+    }
+}
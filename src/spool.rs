@@ -0,0 +1,425 @@
+//! Disk-backed spool for samples that could not be transmitted.
+//!
+//! Unsent samples are written as segments of JSON lines into the spool directory. Segment file names start with the
+//! oldest timestamp of the samples they contain, so replaying segments in file name order replays samples in
+//! timestamp order. The total size of all segments is bounded; if the bound is exceeded, the oldest segments are
+//! dropped first.
+
+use crate::bosun::Sample;
+use crate::config::ConfigError;
+
+use serde_json;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+static SEGMENT_EXTENSION: &'static str = "spool";
+static DEFAULT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct SpoolConfig {
+    /// Directory to store unsent samples in
+    pub Directory: String,
+    /// Max size of all spooled samples in bytes; defaults to 100 MB
+    pub MaxSize: Option<u64>,
+}
+
+impl SpoolConfig {
+    pub fn apply_defaults(&mut self) {
+        self.MaxSize.get_or_insert(DEFAULT_MAX_SIZE);
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.Directory.is_empty() {
+            return Err(ConfigError::invalid("Spool", "Directory", "must not be empty"));
+        }
+        if self.MaxSize == Some(0) {
+            return Err(ConfigError::invalid("Spool", "MaxSize", "must be greater than zero"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of samples that have been spooled, replayed, and dropped since start.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpoolStats {
+    pub spooled: u64,
+    pub replayed: u64,
+    pub dropped: u64,
+}
+
+/// Outcome of passing a batch of spooled samples to `send` during replay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Replayed {
+    /// The batch has been transmitted.
+    Sent,
+    /// The batch will never be accepted, e.g., because it has been rejected, and is dropped.
+    Dropped,
+}
+
+pub struct Spool {
+    directory: PathBuf,
+    max_size: u64,
+    size: u64,
+    next_seq: u64,
+    stats: SpoolStats,
+}
+
+#[derive(Debug)]
+struct Segment {
+    path: PathBuf,
+    seq: u64,
+    size: u64,
+}
+
+impl Spool {
+    /// Opens the spool directory, creating it if necessary. Segments left over by a previous run are picked up.
+    pub fn new(config: &SpoolConfig) -> io::Result<Spool> {
+        let directory = PathBuf::from(&config.Directory);
+        fs::create_dir_all(&directory)?;
+
+        let segments = list_segments(&directory)?;
+        let size = segments.iter().map(|s| s.size).sum();
+        let next_seq = segments.iter().map(|s| s.seq + 1).max().unwrap_or(0);
+        if !segments.is_empty() {
+            info!("Found {} spool segments with {} bytes in '{}'.", segments.len(), size, directory.display());
+        }
+
+        Ok(Spool {
+            directory,
+            max_size: config.MaxSize.unwrap_or(DEFAULT_MAX_SIZE),
+            size,
+            next_seq,
+            stats: SpoolStats::default(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Size of all spooled segments in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn stats(&self) -> &SpoolStats {
+        &self.stats
+    }
+
+    /// Writes samples to a new segment and drops the oldest segments if the spool exceeds its max size.
+    pub fn push(&mut self, mut samples: Vec<Sample>) {
+        if samples.is_empty() {
+            return;
+        }
+        samples.sort_by_key(|s| s.time);
+
+        let len = samples.len() as u64;
+        let path = self.segment_path(samples[0].time);
+        match write_segment(&path, samples.iter()) {
+            Ok(size) => {
+                debug!("Spooled {} samples to '{}'.", len, path.display());
+                self.size += size;
+                self.stats.spooled += len;
+            }
+            Err(err) => {
+                error!("Failed to spool {} samples to '{}', because {}. Dropping samples.", len, path.display(), err);
+                let _ = fs::remove_file(&path);
+                self.stats.dropped += len;
+            }
+        }
+
+        self.enforce_max_size();
+    }
+
+    /// Replays up to `max_samples` spooled samples in timestamp order by passing them in batches of `batch_size` to
+    /// `send`. Replay stops at the first batch `send` fails for; this batch and all following samples stay in the spool.
+    /// Batches `send` drops are removed from the spool like sent batches, but are counted as dropped. Replay also stops
+    /// at a segment that cannot be read; it stays in the spool for the next replay.
+    pub fn replay<F, E>(&mut self, max_samples: usize, batch_size: usize, mut send: F)
+        where F: FnMut(&[Sample]) -> Result<Replayed, E> {
+        let segments = match list_segments(&self.directory) {
+            Ok(segments) => segments,
+            Err(err) => {
+                error!("Failed to list spool directory '{}', because {}.", self.directory.display(), err);
+                return;
+            }
+        };
+
+        let batch_size = batch_size.max(1);
+        let mut budget = max_samples;
+        for segment in segments {
+            if budget == 0 {
+                return;
+            }
+            let samples = match self.read_segment(&segment) {
+                Ok(samples) => samples,
+                Err(err) => {
+                    error!("Failed to read spool segment '{}', because {}. Retrying with next replay.",
+                           segment.path.display(), err);
+                    return;
+                }
+            };
+
+            let mut sent = 0;
+            let mut dropped = 0;
+            while sent < samples.len() && budget > 0 {
+                let len = batch_size.min(budget).min(samples.len() - sent);
+                match send(&samples[sent..sent + len]) {
+                    Ok(Replayed::Sent) => {},
+                    Ok(Replayed::Dropped) => dropped += len,
+                    Err(_) => break,
+                }
+                sent += len;
+                budget -= len;
+            }
+            self.stats.replayed += (sent - dropped) as u64;
+            self.stats.dropped += dropped as u64;
+            debug!("Replayed {} and dropped {} of {} samples from spool segment '{}'.",
+                   sent - dropped, dropped, samples.len(), segment.path.display());
+
+            if sent == samples.len() {
+                self.remove_segment(&segment);
+            } else {
+                self.rewrite_segment(&segment, &samples[sent..]);
+                return;
+            }
+        }
+    }
+
+    /// Writes samples which are still in memory to the spool, e.g., when shutting down.
+    pub fn flush(&mut self, samples: Vec<Sample>) {
+        self.push(samples)
+    }
+
+    fn segment_path(&mut self, oldest_timestamp: u64) -> PathBuf {
+        let name = format!("{:020}-{:010}.{}", oldest_timestamp, self.next_seq, SEGMENT_EXTENSION);
+        self.next_seq += 1;
+        self.directory.join(name)
+    }
+
+    /// Reads all samples of a segment; corrupt samples are dropped, but I/O errors fail the whole segment.
+    fn read_segment(&mut self, segment: &Segment) -> io::Result<Vec<Sample>> {
+        let file = File::open(&segment.path)?;
+
+        let mut samples = Vec::new();
+        for line in BufReader::new(file).lines() {
+            match serde_json::from_str::<Sample>(&line?) {
+                Ok(sample) => samples.push(sample),
+                Err(err) => {
+                    warn!("Dropping corrupt sample in spool segment '{}', because {}.", segment.path.display(), err);
+                    self.stats.dropped += 1;
+                }
+            }
+        }
+
+        Ok(samples)
+    }
+
+    fn rewrite_segment(&mut self, segment: &Segment, samples: &[Sample]) {
+        let tmp_path = segment.path.with_extension("tmp");
+        match write_segment(&tmp_path, samples.iter()).and_then(|size| fs::rename(&tmp_path, &segment.path).map(|_| size)) {
+            Ok(size) => {
+                self.size = self.size.saturating_sub(segment.size) + size;
+            }
+            Err(err) => {
+                error!("Failed to rewrite spool segment '{}', because {}. Dropping {} samples.",
+                       segment.path.display(), err, samples.len());
+                let _ = fs::remove_file(&tmp_path);
+                self.remove_segment(segment);
+                self.stats.dropped += samples.len() as u64;
+            }
+        }
+    }
+
+    fn remove_segment(&mut self, segment: &Segment) {
+        if let Err(err) = fs::remove_file(&segment.path) {
+            error!("Failed to remove spool segment '{}', because {}.", segment.path.display(), err);
+        }
+        self.size -= segment.size.min(self.size);
+    }
+
+    fn enforce_max_size(&mut self) {
+        if self.size <= self.max_size {
+            return;
+        }
+        let segments = match list_segments(&self.directory) {
+            Ok(segments) => segments,
+            Err(err) => {
+                error!("Failed to list spool directory '{}', because {}.", self.directory.display(), err);
+                return;
+            }
+        };
+
+        for segment in segments {
+            if self.size <= self.max_size {
+                break;
+            }
+            let dropped = count_lines(&segment.path);
+            warn!("Spool exceeds max size of {} bytes; dropping {} samples from '{}'.",
+                  self.max_size, dropped, segment.path.display());
+            self.remove_segment(&segment);
+            self.stats.dropped += dropped;
+        }
+    }
+}
+
+fn list_segments(directory: &Path) -> io::Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != SEGMENT_EXTENSION) {
+            continue;
+        }
+        let seq = path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.split('-').nth(1))
+            .and_then(|seq| seq.parse::<u64>().ok());
+        match seq {
+            Some(seq) => {
+                let size = fs::metadata(&path)?.len();
+                segments.push(Segment { path, seq, size });
+            }
+            None => {
+                warn!("Ignoring unexpected file '{}' in spool directory.", path.display());
+            }
+        }
+    }
+    segments.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(segments)
+}
+
+fn write_segment<'a, I: Iterator<Item=&'a Sample>>(path: &Path, samples: I) -> io::Result<u64> {
+    let mut file = File::create(path)?;
+    let mut size = 0u64;
+    for s in samples {
+        let line = serde_json::to_string(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+        size += line.len() as u64 + 1;
+    }
+    file.sync_data()?;
+
+    Ok(size)
+}
+
+fn count_lines(path: &Path) -> u64 {
+    File::open(path)
+        .map(|f| BufReader::new(f).lines().count() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bosun::Tags;
+
+    use mktemp::Temp;
+
+    fn sample(time: u64, value: f64) -> Sample {
+        Sample { time, metric: "test.metric".to_string(), value, tags: Tags::new() }
+    }
+
+    fn spool_config(dir: &Temp, max_size: Option<u64>) -> SpoolConfig {
+        SpoolConfig { Directory: dir.to_path_buf().to_string_lossy().to_string(), MaxSize: max_size }
+    }
+
+    #[test]
+    fn replay_in_timestamp_order() {
+        let dir = Temp::new_dir().unwrap();
+        let mut spool = Spool::new(&spool_config(&dir, None)).unwrap();
+
+        spool.push(vec![sample(30, 3.0), sample(20, 2.0)]);
+        spool.push(vec![sample(10, 1.0)]);
+        assert_eq!(spool.is_empty(), false);
+
+        let mut replayed = Vec::new();
+        spool.replay::<_, ()>(100, 2, |batch| { replayed.extend(batch.iter().map(|s| s.time)); Ok(Replayed::Sent) });
+
+        assert_eq!(replayed, vec![10, 20, 30]);
+        assert_eq!(spool.is_empty(), true);
+        assert_eq!(spool.stats(), &SpoolStats { spooled: 3, replayed: 3, dropped: 0 });
+    }
+
+    #[test]
+    fn replay_keeps_samples_after_failure() {
+        let dir = Temp::new_dir().unwrap();
+        let mut spool = Spool::new(&spool_config(&dir, None)).unwrap();
+        spool.push(vec![sample(10, 1.0), sample(20, 2.0), sample(30, 3.0)]);
+
+        spool.replay(100, 1, |batch| if batch[0].time < 20 { Ok(Replayed::Sent) } else { Err(()) });
+        let mut replayed = Vec::new();
+        spool.replay::<_, ()>(100, 2, |batch| { replayed.extend(batch.iter().map(|s| s.time)); Ok(Replayed::Sent) });
+
+        assert_eq!(replayed, vec![20, 30]);
+        assert_eq!(spool.stats().replayed, 3);
+    }
+
+    #[test]
+    fn replay_counts_dropped_batches() {
+        let dir = Temp::new_dir().unwrap();
+        let mut spool = Spool::new(&spool_config(&dir, None)).unwrap();
+        spool.push(vec![sample(10, 1.0), sample(20, 2.0), sample(30, 3.0)]);
+
+        spool.replay::<_, ()>(100, 1, |batch| if batch[0].time == 20 { Ok(Replayed::Dropped) } else { Ok(Replayed::Sent) });
+
+        assert_eq!(spool.is_empty(), true);
+        assert_eq!(spool.stats(), &SpoolStats { spooled: 3, replayed: 2, dropped: 1 });
+    }
+
+    #[test]
+    fn replay_keeps_unreadable_segments() {
+        let dir = Temp::new_dir().unwrap();
+        let mut spool = Spool::new(&spool_config(&dir, None)).unwrap();
+        spool.push(vec![sample(10, 1.0)]);
+        let segment = list_segments(&dir.to_path_buf()).unwrap().remove(0);
+        // Reading a directory fails like a segment that cannot be opened or read, e.g., because of EMFILE or EIO.
+        fs::remove_file(&segment.path).unwrap();
+        fs::create_dir(&segment.path).unwrap();
+
+        let mut replayed = Vec::new();
+        spool.replay::<_, ()>(100, 2, |batch| { replayed.extend(batch.iter().map(|s| s.time)); Ok(Replayed::Sent) });
+
+        assert!(replayed.is_empty());
+        assert!(segment.path.exists());
+        assert_eq!(spool.stats(), &SpoolStats { spooled: 1, replayed: 0, dropped: 0 });
+    }
+
+    #[test]
+    fn max_size_drops_oldest_segments() {
+        let dir = Temp::new_dir().unwrap();
+        let mut spool = Spool::new(&spool_config(&dir, Some(150))).unwrap();
+
+        spool.push(vec![sample(10, 1.0)]);
+        spool.push(vec![sample(20, 2.0)]);
+        spool.push(vec![sample(30, 3.0)]);
+
+        let mut replayed = Vec::new();
+        spool.replay::<_, ()>(100, 2, |batch| { replayed.extend(batch.iter().map(|s| s.time)); Ok(Replayed::Sent) });
+
+        assert_eq!(spool.stats().dropped, 1);
+        assert_eq!(replayed, vec![20, 30]);
+    }
+
+    #[test]
+    fn survives_reopen() {
+        let dir = Temp::new_dir().unwrap();
+        {
+            let mut spool = Spool::new(&spool_config(&dir, None)).unwrap();
+            spool.push(vec![sample(10, 1.0)]);
+        }
+
+        let mut spool = Spool::new(&spool_config(&dir, None)).unwrap();
+        assert_eq!(spool.is_empty(), false);
+        spool.push(vec![sample(20, 2.0)]);
+
+        let mut replayed = Vec::new();
+        spool.replay::<_, ()>(100, 2, |batch| { replayed.extend(batch.iter().map(|s| s.time)); Ok(Replayed::Sent) });
+        assert_eq!(replayed, vec![10, 20]);
+    }
+}